@@ -0,0 +1,165 @@
+//! Benchmarks over a generated book of several megabytes. Run with
+//! `cargo bench`, and compare against a saved baseline with
+//! `cargo bench -- --save-baseline <name>` and `--baseline <name>`.
+
+use chrono::{Duration, TimeZone, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use scrivenwright::app::App;
+use scrivenwright::glyph_string::GlyphString;
+use scrivenwright::text::{OpenText, TestResult};
+use scrivenwright::text_wrapper::TextWrapper;
+
+const BOOK_BYTES: usize = 4_000_000;
+const TEST_LOG_LEN: usize = 100_000;
+const LINE_WIDTH: u16 = 80;
+
+const WORDS: &[&str] = &[
+    "the",
+    "of",
+    "and",
+    "a",
+    "to",
+    "in",
+    "was",
+    "he",
+    "that",
+    "it",
+    "his",
+    "her",
+    "with",
+    "had",
+    "as",
+    "for",
+    "which",
+    "upon",
+    "herself",
+    "remarkable",
+    "circumstances",
+    "naïve",
+    "café",
+    "well-known",
+    "“quoted”",
+    "—",
+];
+
+/// A deterministic book of paragraphs of between one and twelve sentences.
+fn book() -> String {
+    let mut seed: u64 = 0x5eed;
+    let mut next = move |n: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % n
+    };
+    let mut book = String::with_capacity(BOOK_BYTES + 1000);
+    while book.len() < BOOK_BYTES {
+        for _ in 0..=next(12) {
+            let len = 4 + next(20);
+            for i in 0..len {
+                let word = WORDS[next(WORDS.len())];
+                if i == 0 {
+                    let mut chars = word.chars();
+                    book.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+                    book.push_str(chars.as_str());
+                } else {
+                    book.push(' ');
+                    book.push_str(word);
+                }
+            }
+            book.push_str(". ");
+        }
+        book.push_str("\n\n");
+    }
+    book
+}
+
+/// Alternating failed and successful tests, which end partway through `len`.
+fn test_log(len: usize) -> Vec<TestResult> {
+    let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    (0..TEST_LOG_LEN)
+        .map(|i| {
+            let start_index = (i / 2) * 100 % (len / 2);
+            let succeeded = i % 2 == 1;
+            TestResult {
+                succeeded,
+                start_index,
+                end_index: start_index + if succeeded { 100 } else { 40 },
+                started: t + Duration::seconds(i as i64 * 30),
+                completed: t + Duration::seconds(i as i64 * 30 + 20),
+                pauses: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn glyph_string(c: &mut Criterion) {
+    let book = book();
+    c.bench_function("GlyphString::new", |b| {
+        b.iter_batched(
+            || book.clone(),
+            |book| GlyphString::new(black_box(book)),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn wrapping(c: &mut Criterion) {
+    let text = GlyphString::new(book());
+
+    c.bench_function("TextWrapper full wrap", |b| {
+        b.iter(|| {
+            let wrapper = TextWrapper::new();
+            wrapper.lines(&text, black_box(LINE_WIDTH), 0, 1)
+        })
+    });
+
+    let wrapper = TextWrapper::new();
+    let num_lines = wrapper.lines(&text, LINE_WIDTH, 0, usize::MAX).len();
+    c.bench_function("TextWrapper::lines", |b| {
+        b.iter(|| wrapper.lines(&text, LINE_WIDTH, black_box(num_lines / 2), 60))
+    });
+
+    c.bench_function("TextWrapper::line_offset_of_idx", |b| {
+        b.iter(|| wrapper.line_offset_of_idx(&text, LINE_WIDTH, black_box(text.len() / 2)))
+    });
+}
+
+fn tests(c: &mut Criterion) {
+    let book = book();
+    let len = GlyphString::new(book.clone()).len();
+    let log = test_log(len);
+
+    c.bench_function("OpenText::new with 100k tests", |b| {
+        b.iter_batched(
+            || (book.clone(), log.clone()),
+            |(book, log)| OpenText::new(book, log, |_| Ok(())),
+            BatchSize::LargeInput,
+        )
+    });
+
+    // A wrong keypress fails the test, which saves it and chooses the next.
+    let mut text = OpenText::new(book.clone(), log.clone(), |_| Ok(()));
+    c.bench_function("next_test with 100k tests", |b| {
+        b.iter(|| text.handle_char(black_box('\u{0}')))
+    });
+}
+
+fn render(c: &mut Criterion) {
+    let book = book();
+    let len = GlyphString::new(book.clone()).len();
+    let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let text = OpenText::new(book, vec![TestResult::bookmark(len / 2, t)], |_| Ok(()));
+    let app = App::new(());
+    let mut terminal = Terminal::new(TestBackend::new(200, 60)).unwrap();
+
+    c.bench_function("App::render", |b| {
+        b.iter(|| {
+            terminal.draw(|frame| app.render(&text, frame)).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, glyph_string, wrapping, tests, render);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+//! Compresses the sample books when the `samples` feature is enabled, so that
+//! they take up less of the binaries they are bundled into.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[path = "src/lzss.rs"]
+#[allow(dead_code)]
+mod lzss;
+
+fn main() {
+    println!("cargo:rerun-if-changed=samples");
+    println!("cargo:rerun-if-changed=src/lzss.rs");
+    if env::var_os("CARGO_FEATURE_SAMPLES").is_none() {
+        return;
+    }
+    let out_dir = env::var("OUT_DIR").unwrap();
+    for entry in fs::read_dir("samples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "txt") {
+            let text = fs::read(&path).unwrap();
+            let name = path.with_extension("lz");
+            let name = name.file_name().unwrap();
+            fs::write(Path::new(&out_dir).join(name), lzss::compress(&text)).unwrap();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Wraps arbitrary text at an arbitrary width, and moves the cursor around
+//! it, checking the same invariants as `tests/wrapping.rs`.
+//!
+//! Run with `cargo fuzz run wrap` from the `scrivenwright` directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scrivenwright::glyph_string::GlyphString;
+use scrivenwright::text_wrapper::{Dir, TextWrapper};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&width, rest)) = data.split_first() else {
+        return;
+    };
+    let Some((moves, rest)) = rest.split_first_chunk::<4>() else {
+        return;
+    };
+    let Ok(s) = std::str::from_utf8(rest) else {
+        return;
+    };
+    let width = width as u16;
+    let text = GlyphString::new(s.to_string());
+    let mut wrapper = TextWrapper::new();
+
+    let lines = wrapper.lines(&text, width, 0, usize::MAX);
+    let mut end = 0;
+    for &(start, line_end) in &lines {
+        assert_eq!(start, end);
+        assert!(line_end > start);
+        end = line_end;
+    }
+    assert_eq!(end, text.len());
+
+    for idx in 0..text.len() {
+        let (line, offset) = wrapper.line_offset_of_idx(&text, width, idx).unwrap();
+        assert_eq!(lines[line].0 + offset, idx);
+    }
+
+    let mut cursor = 0;
+    for m in moves.iter().flat_map(|b| [b & 3, (b >> 2) & 3, (b >> 4) & 3, b >> 6]) {
+        let dir = match m {
+            0 => Dir::Up,
+            1 => Dir::Down,
+            2 => Dir::Left,
+            _ => Dir::Right,
+        };
+        if let Some(next) = wrapper.move_cursor(&text, width, cursor, dir) {
+            assert!(next < text.len());
+            cursor = next;
+        }
+    }
+});
@@ -1,14 +1,54 @@
 use crate::settings::Settings;
-use chrono::{DateTime, Utc};
+use crate::text::OpenText;
 use std::error;
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+/// How often frontends call `App::tick`, in milliseconds.
+pub const TICK_RATE_MS: u64 = 250;
+
+/// A book which can be chosen on the library screen.
 pub struct TextHandle {
     pub name: String,
+    pub author: Option<String>,
+    /// The length of the book in glyphs.
     pub length: usize,
-    pub last_opened: DateTime<Utc>,
-    open: Box<dyn FnOnce()>,
+    /// The index of the first glyph which hasn't been typed successfully, or
+    /// None for a bundled sample which hasn't been added to the library.
+    pub position: Option<usize>,
+    pub chapters: usize,
+}
+
+impl TextHandle {
+    pub fn percent_complete(&self) -> Option<f64> {
+        let position = self.position?;
+        Some(if self.length == 0 {
+            100.0
+        } else {
+            position as f64 * 100.0 / self.length as f64
+        })
+    }
+}
+
+/// The library screen, which lists the books the adapter knows about.
+pub struct Library {
+    pub texts: Vec<TextHandle>,
+    pub selected: usize,
+}
+
+impl Library {
+    /// The first item shown when `rows` items fit, which keeps the selected
+    /// item on screen.
+    pub(crate) fn offset(&self, rows: usize) -> usize {
+        (self.selected + 1).saturating_sub(rows)
+    }
+}
+
+/// A glyph which was clicked, shown in a popup until it is dismissed.
+pub struct Inspection {
+    pub index: usize,
+    /// The number of the chapter the glyph is in, if the book has chapters.
+    pub chapter: Option<usize>,
 }
 
 pub trait PlatformAdapter {
@@ -25,6 +65,18 @@ pub struct App<PA: PlatformAdapter> {
     pub running: bool,
     pub adapter: PA,
     pub settings: Settings,
+    /// An error to show to the user until it is dismissed.
+    pub error: Option<String>,
+    /// The title of the open book, which is selected in the library.
+    pub book_title: String,
+    /// Shown instead of the book while it is open.
+    pub library: Option<Library>,
+    /// The name of a book chosen on the library screen, for the frontend to
+    /// open.
+    pub chosen: Option<String>,
+    pub inspected: Option<Inspection>,
+    /// Whether quitting was put off because something couldn't be saved.
+    unsaved_warned: bool,
 }
 
 impl<PA: PlatformAdapter> App<PA> {
@@ -33,10 +85,48 @@ impl<PA: PlatformAdapter> App<PA> {
             adapter,
             settings: Settings::default(),
             running: true,
+            error: None,
+            book_title: String::new(),
+            library: None,
+            chosen: None,
+            inspected: None,
+            unsaved_warned: false,
         }
     }
 
-    pub fn quit(&mut self) {
-        self.running = false;
+    /// Shows the library screen, with the open book selected.
+    pub fn open_library(&mut self) {
+        let texts = self.adapter.get_texts();
+        let selected = texts
+            .iter()
+            .position(|t| t.name == self.book_title)
+            .unwrap_or(0);
+        self.library = Some(Library { texts, selected });
+    }
+
+    /// Called by the frontend every `TICK_RATE_MS`, whether or not there was
+    /// any input, for anything which changes with time. The screen is drawn
+    /// again afterwards.
+    pub fn tick(&mut self, text: &mut OpenText) {
+        if let Some(after) = self.settings.idle_timeout() {
+            text.pause_if_idle(after);
+        }
+        if text.save_error().is_some() {
+            text.retry_saves();
+        }
+    }
+
+    /// Stops the app, unless something couldn't be saved, in which case the
+    /// first attempt only warns about it.
+    pub fn quit(&mut self, text: &mut OpenText) {
+        if text.retry_saves() || self.unsaved_warned {
+            self.running = false;
+            return;
+        }
+        self.unsaved_warned = true;
+        self.error = Some(format!(
+            "{}. Press Ctrl-C again to quit without saving.",
+            text.save_error().unwrap_or_default()
+        ));
     }
 }
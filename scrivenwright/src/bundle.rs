@@ -0,0 +1,142 @@
+use crate::app::AppResult;
+use crate::glyph_string::GlyphString;
+use crate::schema::{self, Versioned};
+use crate::settings::Settings;
+use crate::text::{KeyPress, TestResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A portable archive of one or more books and everything recorded about
+/// them, which either frontend can export and import.
+#[derive(Serialize, Deserialize)]
+pub struct Bundle {
+    pub exported: DateTime<Utc>,
+    pub settings: Option<Settings>,
+    pub books: Vec<BookBundle>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BookBundle {
+    pub title: String,
+    pub text: String,
+    pub metadata: BookMetadata,
+    #[serde(with = "schema::versioned_vec")]
+    pub tests: Vec<TestResult>,
+    #[serde(with = "schema::versioned_vec")]
+    pub keypresses: Vec<KeyPress>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BookMetadata {
+    /// The length of the text in glyphs.
+    pub length: usize,
+    pub last_practiced: Option<DateTime<Utc>>,
+}
+
+impl BookBundle {
+    pub fn new(
+        title: String,
+        text: String,
+        tests: Vec<TestResult>,
+        keypresses: Vec<KeyPress>,
+    ) -> Self {
+        let metadata = BookMetadata {
+            length: GlyphString::new(text.clone()).len(),
+            last_practiced: tests.iter().map(|t| t.completed).max(),
+        };
+        Self {
+            title,
+            text,
+            metadata,
+            tests,
+            keypresses,
+        }
+    }
+
+    /// Merges this book's logs into logs which are already stored locally.
+    /// Fails if the local copy of the book has a different text, as the
+    /// recorded positions would no longer line up.
+    pub fn merge_into(
+        self,
+        local_text: Option<&str>,
+        tests: &mut Vec<TestResult>,
+        keypresses: &mut Vec<KeyPress>,
+    ) -> AppResult<()> {
+        if local_text.is_some_and(|t| t != self.text) {
+            return Err(format!("{} has a different text on this device", self.title).into());
+        }
+        *tests = merge_logs(tests, &self.tests);
+        *keypresses = merge_logs(keypresses, &self.keypresses);
+        Ok(())
+    }
+}
+
+impl Bundle {
+    pub fn new(settings: Option<Settings>, books: Vec<BookBundle>) -> Self {
+        Self {
+            exported: Utc::now(),
+            settings,
+            books,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        schema::encode(self)
+    }
+
+    pub fn from_json(json: &str) -> AppResult<Self> {
+        Ok(schema::decode(serde_json::from_str(json)?)?.0)
+    }
+}
+
+fn v0_to_v1(_: &mut Map<String, Value>) {}
+
+impl Versioned for Bundle {
+    const VERSION: u32 = 1;
+    const MIGRATIONS: &'static [fn(&mut Map<String, Value>)] = &[v0_to_v1];
+}
+
+/// A record which is ordered by the time it was made. Several records can
+/// share a time, such as the keypresses of a paste.
+pub trait Timestamped: Clone + PartialEq {
+    fn timestamp(&self) -> DateTime<Utc>;
+}
+
+impl Timestamped for TestResult {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.completed
+    }
+}
+
+impl Timestamped for KeyPress {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.time
+    }
+}
+
+/// Combines two logs in timestamp order. A record of `theirs` is dropped if
+/// an identical record in `ours` hasn't already matched another, so records
+/// which are in both logs are kept once, but neither log loses any of its
+/// own records.
+pub fn merge_logs<T: Timestamped>(ours: &[T], theirs: &[T]) -> Vec<T> {
+    let mut by_time = (0..ours.len()).collect::<Vec<_>>();
+    by_time.sort_by_key(|&i| ours[i].timestamp());
+    let mut matched = vec![false; ours.len()];
+
+    let mut merged = ours.to_vec();
+    for record in theirs {
+        let first = by_time.partition_point(|&i| ours[i].timestamp() < record.timestamp());
+        let copy = by_time[first..]
+            .iter()
+            .take_while(|&&i| ours[i].timestamp() == record.timestamp())
+            .find(|&&i| !matched[i] && ours[i] == *record);
+        match copy {
+            Some(&i) => matched[i] = true,
+            None => merged.push(record.clone()),
+        }
+    }
+    // The sort is stable, so records with the same time stay in log order.
+    merged.sort_by_key(|r| r.timestamp());
+    merged
+}
@@ -0,0 +1,104 @@
+use crate::glyph_string::GlyphString;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A line which starts with "Chapter" and a number, in digits or roman
+/// numerals. The number ends the line or is followed by punctuation, as in
+/// "Chapter 2. Dawn", so that "Chapter mix of things" isn't a heading.
+const HEADING: &str =
+    r"(?m)^(?i:chapter)[ \t]+([0-9]+|[ivxlcdm]+|[IVXLCDM]+)[ \t]*(?:[.:;,\-–—][^\n]*)?\r?$";
+
+/// The chapter headings of a book, in order, as the chapter's number and the
+/// glyph index where its heading starts.
+///
+/// Books often list their chapters in a table of contents before the first
+/// chapter, so when a number is used more than once the last heading with
+/// that number is kept.
+pub fn chapters(text: &GlyphString) -> Vec<(usize, usize)> {
+    static HEADING_RE: OnceLock<Regex> = OnceLock::new();
+    let heading = HEADING_RE.get_or_init(|| Regex::new(HEADING).unwrap());
+    let mut found: Vec<(usize, usize)> = Vec::new();
+    for captures in heading.captures_iter(text.as_str()) {
+        let Some(number) = parse_number(&captures[1]) else {
+            continue;
+        };
+        let index = text.index_of_ptr(captures.get(0).map_or(0, |m| m.start()));
+        found.retain(|&(n, _)| n != number);
+        found.push((number, index));
+    }
+    found.sort_by_key(|&(_, index)| index);
+    found
+}
+
+/// The glyph index where chapter `number` starts, given a book's `chapters`.
+pub fn chapter_start(chapters: &[(usize, usize)], number: usize) -> Option<usize> {
+    chapters
+        .iter()
+        .find(|&&(n, _)| n == number)
+        .map(|&(_, index)| index)
+}
+
+/// The number of the chapter which the glyph at `index` is in, given a
+/// book's `chapters`.
+pub fn chapter_at(chapters: &[(usize, usize)], index: usize) -> Option<usize> {
+    chapters
+        .iter()
+        .take_while(|&&(_, start)| start <= index)
+        .last()
+        .map(|&(n, _)| n)
+}
+
+fn parse_number(numeral: &str) -> Option<usize> {
+    if let Ok(n) = numeral.parse() {
+        return Some(n);
+    }
+    let mut total: usize = 0;
+    let mut previous = 0;
+    for c in numeral.chars().rev() {
+        let value = match c.to_ascii_uppercase() {
+            'I' => 1,
+            'V' => 5,
+            'X' => 10,
+            'L' => 50,
+            'C' => 100,
+            'D' => 500,
+            'M' => 1000,
+            _ => return None,
+        };
+        if value < previous {
+            total = total.checked_sub(value)?;
+        } else {
+            total += value;
+            previous = value;
+        }
+    }
+    // Only numerals in their usual form, so that words such as "dim" aren't
+    // taken for numbers.
+    (total > 0 && to_roman(total).eq_ignore_ascii_case(numeral)).then_some(total)
+}
+
+fn to_roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut roman = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            roman.push_str(numeral);
+            n -= value;
+        }
+    }
+    roman
+}
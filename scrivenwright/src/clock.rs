@@ -0,0 +1,41 @@
+use chrono::{DateTime, Duration, Utc};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// A source of the current time, so that timing can be controlled in tests.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock which only moves when told to. Clones share the same time, so a
+/// test can keep one and give another to the code under test.
+#[derive(Clone)]
+pub struct ManualClock(Rc<Cell<DateTime<Utc>>>);
+
+impl ManualClock {
+    pub fn new(time: DateTime<Utc>) -> Self {
+        Self(Rc::new(Cell::new(time)))
+    }
+
+    pub fn set(&self, time: DateTime<Utc>) {
+        self.0.set(time)
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.0.set(self.0.get() + duration)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0.get()
+    }
+}
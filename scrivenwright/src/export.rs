@@ -0,0 +1,186 @@
+use crate::glyph_string::GlyphString;
+use crate::text::{KeyPress, TestResult};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "ndjson",
+        }
+    }
+}
+
+/// Everything recorded for one book.
+pub struct History<'a> {
+    pub title: &'a str,
+    pub text: &'a str,
+    pub tests: &'a [TestResult],
+    pub keypresses: &'a [KeyPress],
+}
+
+#[derive(Serialize)]
+struct TestRow<'a> {
+    book: &'a str,
+    succeeded: bool,
+    start_index: usize,
+    end_index: usize,
+    started: DateTime<Utc>,
+    completed: DateTime<Utc>,
+    duration_secs: f64,
+    wpm: f64,
+    accuracy: Option<f64>,
+    passage: String,
+}
+
+impl TestRow<'_> {
+    const HEADER: &'static [&'static str] = &[
+        "book",
+        "succeeded",
+        "start_index",
+        "end_index",
+        "started",
+        "completed",
+        "duration_secs",
+        "wpm",
+        "accuracy",
+        "passage",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.book.to_string(),
+            self.succeeded.to_string(),
+            self.start_index.to_string(),
+            self.end_index.to_string(),
+            self.started.to_rfc3339(),
+            self.completed.to_rfc3339(),
+            self.duration_secs.to_string(),
+            self.wpm.to_string(),
+            self.accuracy.map(|a| a.to_string()).unwrap_or_default(),
+            self.passage.clone(),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct KeyPressRow<'a> {
+    book: &'a str,
+    time: DateTime<Utc>,
+    key: &'a str,
+    correct: bool,
+}
+
+impl KeyPressRow<'_> {
+    const HEADER: &'static [&'static str] = &["book", "time", "key", "correct"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.book.to_string(),
+            self.time.to_rfc3339(),
+            self.key.to_string(),
+            self.correct.to_string(),
+        ]
+    }
+}
+
+pub fn write_tests<W: Write>(books: &[History], format: Format, out: &mut W) -> io::Result<()> {
+    if format == Format::Csv {
+        write_csv_row(out, TestRow::HEADER.iter())?;
+    }
+    for book in books {
+        let text = GlyphString::new(book.text.to_string());
+        let mut keypresses = book.keypresses.to_vec();
+        keypresses.sort_by_key(|k| k.time);
+
+        // Bookmarks only move where the next test starts, so they aren't
+        // exported as tests.
+        for test in book.tests.iter().filter(|t| !t.is_empty()) {
+            let first = keypresses.partition_point(|k| k.time < test.started);
+            let last = keypresses.partition_point(|k| k.time <= test.completed);
+            let during = &keypresses[first..last.max(first)];
+            let accuracy = (!during.is_empty())
+                .then(|| during.iter().filter(|k| k.correct).count() as f64 / during.len() as f64);
+
+            let row = TestRow {
+                book: book.title,
+                succeeded: test.succeeded,
+                start_index: test.start_index,
+                end_index: test.end_index,
+                started: test.started,
+                completed: test.completed,
+                duration_secs: test.duration_secs(),
+                wpm: test.wpm(),
+                accuracy,
+                passage: text
+                    .glyphs()
+                    .skip(test.start_index)
+                    .take(test.len())
+                    .collect(),
+            };
+            match format {
+                Format::Csv => write_csv_row(out, row.fields().iter())?,
+                Format::Json => write_json_row(out, &row)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn write_keypresses<W: Write>(
+    books: &[History],
+    format: Format,
+    out: &mut W,
+) -> io::Result<()> {
+    if format == Format::Csv {
+        write_csv_row(out, KeyPressRow::HEADER.iter())?;
+    }
+    for book in books {
+        for keypress in book.keypresses {
+            let row = KeyPressRow {
+                book: book.title,
+                time: keypress.time,
+                key: &keypress.key,
+                correct: keypress.correct,
+            };
+            match format {
+                Format::Csv => write_csv_row(out, row.fields().iter())?,
+                Format::Json => write_json_row(out, &row)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_csv_row<W, I, S>(out: &mut W, fields: I) -> io::Result<()>
+where
+    W: Write,
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    let row = fields
+        .map(|f| {
+            let f = f.as_ref();
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(out, "{}", row)
+}
+
+fn write_json_row<W: Write, R: Serialize>(out: &mut W, row: &R) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, row)?;
+    writeln!(out)
+}
@@ -2,10 +2,11 @@ use std::ops::Index;
 use std::slice::Iter;
 use unicode_segmentation::UnicodeSegmentation;
 
-/// String indexed by unicode segments
+/// String indexed by unicode segments. The fields are private so that the
+/// glyph boundaries always match the string.
 pub struct GlyphString {
-    pub string: String,
-    pub glyphs: Box<[(usize, usize)]>,
+    string: String,
+    glyphs: Box<[(usize, usize)]>,
 }
 
 impl GlyphString {
@@ -24,10 +25,23 @@ impl GlyphString {
         }
     }
 
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// The start and end byte offsets of each glyph in the string.
+    pub fn byte_ranges(&self) -> &[(usize, usize)] {
+        &self.glyphs
+    }
+
     pub fn len(&self) -> usize {
         self.glyphs.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
     pub fn index_of_ptr(&self, ptr: usize) -> usize {
         match self.glyphs.binary_search_by_key(&ptr, |&(start, _)| start) {
             Ok(idx) => idx,
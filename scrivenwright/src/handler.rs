@@ -1,7 +1,9 @@
-use crate::app::{App, PlatformAdapter};
+use crate::app::{App, Inspection, PlatformAdapter};
+use crate::chapters;
 use crate::settings::{DEFAULT_TEXT_WIDTH_PERCENT, FULL_TEXT_WIDTH_PERCENT};
 use crate::text::OpenText;
 use crate::text_wrapper::Dir;
+use ratatui::layout::Rect;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct KeyDown {
@@ -26,6 +28,17 @@ pub enum KeyModifiers {
     Unimplemented,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MouseEvent {
+    ScrollUp,
+    ScrollDown,
+    /// A click with the primary button on a cell of the screen.
+    Click {
+        column: u16,
+        row: u16,
+    },
+}
+
 //TODO extract behavior into functions so this is just a key map.
 impl<PA: PlatformAdapter> App<PA> {
     pub fn handle_key_events(
@@ -36,8 +49,24 @@ impl<PA: PlatformAdapter> App<PA> {
     ) {
         use KeyCode as C;
         use KeyModifiers as M;
+        if self.library.is_some() {
+            self.handle_library_keys(key_press, text);
+            return;
+        }
+        if let Some(inspected) = self.inspected.take() {
+            match (key_press.mods, key_press.code) {
+                (_, C::Char('\n')) => {
+                    text.move_to(inspected.index);
+                    return;
+                }
+                (_, C::Esc) => return,
+                // Anything else closes the popup and is handled as usual.
+                _ => {}
+            }
+        }
         match (key_press.mods, key_press.code) {
-            (M::Ctrl, C::Char('c')) => self.quit(),
+            (M::Ctrl, C::Char('c')) => self.quit(text),
+            (M::Ctrl, C::Char('l')) => self.open_library(),
             (M::Ctrl, C::Char('f')) => {
                 self.settings.full_text_width = !self.settings.full_text_width;
                 self.settings.text_width_percent = if self.settings.full_text_width {
@@ -59,8 +88,96 @@ impl<PA: PlatformAdapter> App<PA> {
             (_, C::Right) => {
                 text.move_cursor(self.settings.line_width(terminal_width), Dir::Right);
             }
-            (_, C::Esc) => text.snap_to_cursor(),
+            (_, C::Esc) => {
+                self.error = None;
+                text.snap_to_cursor()
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_library_keys(&mut self, key_press: KeyDown, text: &mut OpenText) {
+        use KeyCode as C;
+        use KeyModifiers as M;
+        let Some(library) = self.library.as_mut() else {
+            return;
+        };
+        match (key_press.mods, key_press.code) {
+            (M::Ctrl, C::Char('c')) => self.quit(text),
+            (M::Ctrl, C::Char('l')) | (_, C::Esc) => self.library = None,
+            (_, C::Up) => library.selected = library.selected.saturating_sub(1),
+            (_, C::Down) => {
+                library.selected = (library.selected + 1).min(library.texts.len().saturating_sub(1))
+            }
+            (_, C::Char('\n')) => {
+                self.chosen = library.texts.get(library.selected).map(|t| t.name.clone());
+                self.library = None;
+            }
             _ => {}
         }
     }
+
+    /// Scrolls the book or the library with the wheel. Clicking a glyph
+    /// focuses it and shows a popup about it, and clicking an item in the
+    /// library selects it, or opens it if it was already selected.
+    pub fn handle_mouse_events(&mut self, event: MouseEvent, text: &mut OpenText, screen: Rect) {
+        use MouseEvent as E;
+        if self.library.is_some() {
+            self.handle_library_mouse(event, screen);
+            return;
+        }
+        let line_width = self.settings.line_width(screen.width.saturating_sub(2));
+        self.inspected = None;
+        match event {
+            E::ScrollUp => text.move_cursor(line_width, Dir::Up),
+            E::ScrollDown => text.move_cursor(line_width, Dir::Down),
+            E::Click { column, row } => {
+                if let Some(index) = self.glyph_at(text, screen, column, row) {
+                    text.focused_glyph = index;
+                    self.inspected = Some(Inspection {
+                        index,
+                        chapter: chapters::chapter_at(text.chapters(), index),
+                    });
+                }
+            }
+        }
+    }
+
+    fn handle_library_mouse(&mut self, event: MouseEvent, screen: Rect) {
+        use MouseEvent as E;
+        let Some(library) = self.library.as_mut() else {
+            return;
+        };
+        let last = library.texts.len().saturating_sub(1);
+        match event {
+            E::ScrollUp => library.selected = library.selected.saturating_sub(1),
+            E::ScrollDown => library.selected = (library.selected + 1).min(last),
+            E::Click { column, row } => {
+                // The items are drawn inside the border, one per row.
+                let rows = screen.height.saturating_sub(2);
+                if column == 0 || column + 1 >= screen.width || row == 0 || row > rows {
+                    return;
+                }
+                let index = library.offset(rows as usize) + row as usize - 1;
+                if index > last || library.texts.is_empty() {
+                    return;
+                }
+                if index == library.selected {
+                    self.chosen = Some(library.texts[index].name.clone());
+                    self.library = None;
+                } else {
+                    library.selected = index;
+                }
+            }
+        }
+    }
+
+    /// Handles text entered all at once, such as a string committed by an
+    /// input method or a paste.
+    pub fn handle_text(&mut self, input: &str, text: &mut OpenText) {
+        if self.library.is_none() {
+            self.inspected = None;
+            text.handle_input(input);
+        }
+    }
 }
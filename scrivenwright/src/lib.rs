@@ -1,7 +1,17 @@
 pub mod app;
-pub(crate) mod glyph_string;
+pub mod bundle;
+pub mod chapters;
+pub mod clock;
+pub mod export;
+pub mod glyph_string;
 pub mod handler;
+pub mod lzss;
+#[cfg(feature = "samples")]
+pub mod samples;
+pub mod schema;
 pub mod settings;
+pub mod stats;
 pub mod text;
-pub(crate) mod text_wrapper;
+pub mod text_wrapper;
+pub mod theme;
 pub mod ui;
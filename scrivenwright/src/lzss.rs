@@ -0,0 +1,97 @@
+//! A small LZSS compressor for the bundled sample books. It is used by the
+//! build script as well as the library, so it only depends on `std`.
+//!
+//! The output is a series of groups, each a flag byte followed by eight
+//! items. A clear flag bit means the item is a literal byte, and a set bit
+//! means it is a two byte back reference: a 12 bit distance and a 4 bit
+//! length, which copies `length + MIN_MATCH` bytes from `distance + 1` bytes
+//! back.
+
+const WINDOW: usize = 1 << 12;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 15;
+/// How many earlier positions with the same prefix are tried for a match.
+const MAX_CHAIN: usize = 256;
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2);
+    // The most recent position of each three byte prefix, and for each
+    // position the one before it with the same prefix.
+    let mut head = vec![usize::MAX; 1 << 16];
+    let mut previous = vec![usize::MAX; input.len()];
+    let hash = |i: usize| {
+        ((input[i] as usize) << 8 ^ (input[i + 1] as usize) << 4 ^ input[i + 2] as usize) & 0xFFFF
+    };
+
+    let mut flags_at = 0;
+    let mut item = 8;
+    let mut i = 0;
+    while i < input.len() {
+        if item == 8 {
+            flags_at = output.len();
+            output.push(0);
+            item = 0;
+        }
+
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + MIN_MATCH <= input.len() {
+            let mut candidate = head[hash(i)];
+            let mut tries = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && tries < MAX_CHAIN {
+                let len = input[candidate..]
+                    .iter()
+                    .zip(&input[i..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    (best_len, best_distance) = (len, i - candidate);
+                }
+                candidate = previous[candidate];
+                tries += 1;
+            }
+        }
+
+        let step = if best_len >= MIN_MATCH {
+            output[flags_at] |= 1 << item;
+            let code = (best_distance - 1) << 4 | (best_len - MIN_MATCH);
+            output.extend_from_slice(&(code as u16).to_le_bytes());
+            best_len
+        } else {
+            output.push(input[i]);
+            1
+        };
+        for j in (i..i + step).filter(|j| j + MIN_MATCH <= input.len()) {
+            let h = hash(j);
+            previous[j] = head[h];
+            head[h] = j;
+        }
+        i += step;
+        item += 1;
+    }
+    output
+}
+
+/// Reverses `compress`, or returns None if `input` wasn't made by it.
+pub fn decompress(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 2);
+    let mut bytes = input.iter().copied();
+    while let Some(flags) = bytes.next() {
+        for item in 0..8 {
+            if flags & 1 << item == 0 {
+                match bytes.next() {
+                    Some(b) => output.push(b),
+                    None => return Some(output),
+                }
+            } else {
+                let code = u16::from_le_bytes([bytes.next()?, bytes.next()?]) as usize;
+                let distance = (code >> 4) + 1;
+                let start = output.len().checked_sub(distance)?;
+                for j in start..start + (code & 0xF) + MIN_MATCH {
+                    output.push(output[j]);
+                }
+            }
+        }
+    }
+    Some(output)
+}
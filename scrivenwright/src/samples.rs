@@ -0,0 +1,61 @@
+//! Short public domain texts which are bundled into the binaries, so that
+//! practice can start without finding and preparing a book: the opening
+//! chapters of a novel, a short story, a poem and a speech. They are stored
+//! already normalized, with paragraphs separated by blank lines. Long works
+//! are excerpted to keep the binaries small, and say so in their titles.
+
+use crate::chapters;
+use crate::glyph_string::GlyphString;
+use crate::lzss;
+
+pub struct Sample {
+    pub title: &'static str,
+    pub author: &'static str,
+    compressed: &'static [u8],
+}
+
+macro_rules! sample {
+    ($title:literal, $author:literal, $file:literal) => {
+        Sample {
+            title: $title,
+            author: $author,
+            compressed: include_bytes!(concat!(env!("OUT_DIR"), "/", $file, ".lz")),
+        }
+    };
+}
+
+pub const SAMPLES: &[Sample] = &[
+    sample!(
+        "Pride and Prejudice (Chapters 1-2)",
+        "Jane Austen",
+        "pride-and-prejudice"
+    ),
+    sample!(
+        "The Tell-Tale Heart",
+        "Edgar Allan Poe",
+        "the-tell-tale-heart"
+    ),
+    sample!("The Raven", "Edgar Allan Poe", "the-raven"),
+    sample!(
+        "The Gettysburg Address",
+        "Abraham Lincoln",
+        "the-gettysburg-address"
+    ),
+];
+
+impl Sample {
+    pub fn text(&self) -> String {
+        lzss::decompress(self.compressed)
+            .and_then(|text| String::from_utf8(text).ok())
+            .unwrap_or_default()
+    }
+
+    /// The chapter headings of the book, as in [`chapters::chapters`].
+    pub fn chapters(&self) -> Vec<(usize, usize)> {
+        chapters::chapters(&GlyphString::new(self.text()))
+    }
+}
+
+pub fn find(title: &str) -> Option<&'static Sample> {
+    SAMPLES.iter().find(|s| s.title == title)
+}
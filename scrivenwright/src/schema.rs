@@ -0,0 +1,251 @@
+use serde::{de, de::DeserializeOwned, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::{error, fmt};
+
+const VERSION_KEY: &str = "version";
+
+/// A record which is persisted with a schema version, so that data written by
+/// older builds can be upgraded when it is loaded.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// The version written by this build.
+    const VERSION: u32;
+    /// `MIGRATIONS[n]` upgrades a record from version `n` to version `n + 1`.
+    /// Records written before versioning was introduced are version 0.
+    const MIGRATIONS: &'static [fn(&mut Map<String, Value>)];
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Json(serde_json::Error),
+    NotAnObject,
+    /// The version isn't a non-negative integer.
+    InvalidVersion(Value),
+    UnknownVersion(u64),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Json(e) => write!(f, "{}", e),
+            DecodeError::NotAnObject => write!(f, "record is not a JSON object"),
+            DecodeError::InvalidVersion(v) => write!(f, "record has an invalid version, {}", v),
+            DecodeError::UnknownVersion(v) => {
+                write!(f, "record has version {}, which this build can't read", v)
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+impl From<serde_json::Error> for DecodeError {
+    fn from(e: serde_json::Error) -> Self {
+        DecodeError::Json(e)
+    }
+}
+
+/// A line of a log which could not be decoded. The original content is kept
+/// so that it can be written back out rather than lost.
+#[derive(Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub content: String,
+    pub error: DecodeError,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+pub struct DecodedLog<T> {
+    pub records: Vec<T>,
+    pub errors: Vec<LineError>,
+    /// Whether any record was stored in an older format and the log should
+    /// be rewritten.
+    pub outdated: bool,
+}
+
+pub fn encode<T: Versioned>(record: &T) -> serde_json::Result<String> {
+    serde_json::to_string(&encode_value(record)?)
+}
+
+pub fn encode_value<T: Versioned>(record: &T) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(record)?;
+    if let Value::Object(map) = &mut value {
+        map.insert(VERSION_KEY.into(), T::VERSION.into());
+    }
+    Ok(value)
+}
+
+/// Decodes a single record, applying any migrations it needs. The returned
+/// flag is set if the record was upgraded from an older version.
+pub fn decode<T: Versioned>(value: Value) -> Result<(T, bool), DecodeError> {
+    debug_assert_eq!(T::MIGRATIONS.len(), T::VERSION as usize);
+    let Value::Object(mut map) = value else {
+        return Err(DecodeError::NotAnObject);
+    };
+    let version = match map.remove(VERSION_KEY) {
+        None => 0,
+        Some(v) => v.as_u64().ok_or(DecodeError::InvalidVersion(v))?,
+    };
+    if version > T::VERSION as u64 {
+        return Err(DecodeError::UnknownVersion(version));
+    }
+    for migration in &T::MIGRATIONS[version as usize..T::VERSION as usize] {
+        migration(&mut map);
+    }
+    let record = serde_json::from_value(Value::Object(map))?;
+    Ok((record, version < T::VERSION as u64))
+}
+
+/// Decodes a newline delimited log of records. Logs which were stored as a
+/// single JSON array, as keypresses used to be, are also accepted and marked
+/// as outdated.
+///
+/// Damaged logs are salvaged. If an array was written over a longer one, the
+/// records in the array are kept and anything after it is decoded line by
+/// line. Records which were appended to the end of a damaged line are also
+/// recovered, and the log is marked as outdated so that it is rewritten with
+/// the damage on a line of its own.
+pub fn decode_log<T: Versioned>(contents: &str) -> DecodedLog<T> {
+    let mut log = DecodedLog {
+        records: Vec::new(),
+        errors: Vec::new(),
+        outdated: false,
+    };
+
+    let mut rest = contents;
+    let mut first_line = 1;
+    if contents.trim_start().starts_with('[') {
+        log.outdated = true;
+        let end = log.decode_array(contents);
+        first_line += contents[..end].matches('\n').count();
+        rest = &contents[end..];
+    }
+
+    for (i, line) in rest.split('\n').enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(error) => {
+                log.push_damaged(first_line + i, line, error);
+                continue;
+            }
+        };
+        log.push(first_line + i, line.to_string(), decode(value));
+    }
+    log
+}
+
+/// Splits a line which isn't a record into the damage at its start, and the
+/// records which were appended after the damage.
+fn salvage(line: &str) -> Option<(&str, Vec<Value>)> {
+    line.match_indices('{').find_map(|(start, _)| {
+        let records = serde_json::Deserializer::from_str(&line[start..])
+            .into_iter::<Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let objects = !records.is_empty() && records.iter().all(Value::is_object);
+        objects.then(|| (&line[..start], records))
+    })
+}
+
+impl<T: Versioned> DecodedLog<T> {
+    /// Decodes the records of a log stored as a JSON array, up to the end of
+    /// the array or the first damaged record. Returns where it stopped, which
+    /// is the start if no record could be decoded, so that the damage is kept
+    /// whole.
+    fn decode_array(&mut self, contents: &str) -> usize {
+        let mut end = contents.len() - contents.trim_start().len() + 1;
+        let skip_space = |end: usize| contents.len() - contents[end..].trim_start().len();
+        let mut record = 0;
+        loop {
+            record += 1;
+            end = skip_space(end);
+            if contents[end..].starts_with(']') {
+                return end + 1;
+            }
+            let mut values =
+                serde_json::Deserializer::from_str(&contents[end..]).into_iter::<Value>();
+            let Some(Ok(value)) = values.next() else {
+                return if record == 1 { 0 } else { end };
+            };
+            let line = contents[..end].matches('\n').count() + 1;
+            self.push(line, value.to_string(), decode(value));
+            end = skip_space(end + values.byte_offset());
+            match contents[end..].chars().next() {
+                Some(',') => end += 1,
+                Some(']') => return end + 1,
+                _ => return end,
+            }
+        }
+    }
+
+    /// Keeps a line which isn't JSON as an error, along with any records
+    /// which were appended to it.
+    fn push_damaged(&mut self, line: usize, content: &str, error: serde_json::Error) {
+        let Some((damage, records)) = salvage(content) else {
+            self.push(line, content.to_string(), Err(error.into()));
+            return;
+        };
+        self.outdated = true;
+        if !damage.trim().is_empty() {
+            self.push(line, damage.to_string(), Err(error.into()));
+        }
+        for value in records {
+            self.push(line, value.to_string(), decode(value));
+        }
+    }
+}
+
+impl<T> DecodedLog<T> {
+    fn push(&mut self, line: usize, content: String, decoded: Result<(T, bool), DecodeError>) {
+        match decoded {
+            Ok((record, upgraded)) => {
+                self.outdated |= upgraded;
+                self.records.push(record);
+            }
+            Err(error) => self.errors.push(LineError {
+                line,
+                content,
+                error,
+            }),
+        }
+    }
+}
+
+/// Serde helpers for embedding a list of versioned records in a larger
+/// document, for use with `#[serde(with = "schema::versioned_vec")]`.
+pub mod versioned_vec {
+    use super::*;
+
+    pub fn serialize<S, T>(records: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Versioned,
+    {
+        let values = records
+            .iter()
+            .map(encode_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ser::Error::custom)?;
+        values.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Versioned,
+    {
+        Vec::<Value>::deserialize(deserializer)?
+            .into_iter()
+            .map(|v| decode(v).map(|(record, _)| record))
+            .collect::<Result<_, _>>()
+            .map_err(de::Error::custom)
+    }
+}
@@ -1,9 +1,20 @@
+use crate::theme::Theme;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
 pub const DEFAULT_TEXT_WIDTH_PERCENT: u16 = 60;
 pub const FULL_TEXT_WIDTH_PERCENT: u16 = 96;
+pub const DEFAULT_IDLE_SECS: u32 = 10;
 
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
 pub struct Settings {
     pub text_width_percent: u16,
     pub full_text_width: bool,
+    pub theme: Theme,
+    /// A test is paused after this many seconds without a keypress, or never
+    /// if it is 0.
+    pub idle_secs: u32,
 }
 
 impl Settings {
@@ -11,12 +22,18 @@ impl Settings {
         Self {
             text_width_percent: DEFAULT_TEXT_WIDTH_PERCENT,
             full_text_width: false,
+            theme: Theme::default(),
+            idle_secs: DEFAULT_IDLE_SECS,
         }
     }
 
     pub(crate) fn line_width(&self, terminal_width: u16) -> u16 {
         ((terminal_width as usize) * (self.text_width_percent as usize) / 100) as u16
     }
+
+    pub(crate) fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_secs > 0).then(|| Duration::seconds(self.idle_secs as i64))
+    }
 }
 
 impl Default for Settings {
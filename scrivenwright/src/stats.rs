@@ -0,0 +1,137 @@
+use crate::glyph_string::GlyphString;
+use crate::text::TestResult;
+use std::collections::VecDeque;
+
+/// Tests this short or shorter are left out of the rolling statistics, as
+/// they're usually slips rather than real attempts.
+const MIN_COUNTED_LEN: usize = 5;
+const AVERAGE_WINDOW: usize = 50;
+const RECENT_WINDOW: usize = 10;
+
+/// Statistics over the most recent tests which test sizing and the display
+/// need, kept up to date as each test is recorded so that they cost the same
+/// however long the history is.
+pub struct RollingStats {
+    /// The lengths of the last `AVERAGE_WINDOW` counted tests, oldest first.
+    lengths: VecDeque<usize>,
+    sum_average_window: usize,
+    sum_recent_window: usize,
+    failure_streak: usize,
+    position: usize,
+}
+
+impl RollingStats {
+    pub fn new(tests: &[TestResult]) -> Self {
+        let mut stats = Self {
+            lengths: VecDeque::with_capacity(AVERAGE_WINDOW + 1),
+            sum_average_window: 0,
+            sum_recent_window: 0,
+            failure_streak: 0,
+            position: 0,
+        };
+        for test in tests {
+            stats.push(test);
+        }
+        stats
+    }
+
+    pub fn push(&mut self, test: &TestResult) {
+        if test.succeeded {
+            self.position = test.end_index;
+            // Bookmarks only move the position, and say nothing of how
+            // typing is going.
+            if !test.is_empty() {
+                self.failure_streak = 0;
+            }
+        }
+
+        let len = test.len();
+        if len <= MIN_COUNTED_LEN {
+            return;
+        }
+        if !test.succeeded {
+            self.failure_streak += 1;
+        }
+
+        self.lengths.push_back(len);
+        self.sum_average_window += len;
+        self.sum_recent_window += len;
+        if self.lengths.len() > RECENT_WINDOW {
+            self.sum_recent_window -= self.lengths[self.lengths.len() - 1 - RECENT_WINDOW];
+        }
+        if self.lengths.len() > AVERAGE_WINDOW {
+            self.sum_average_window -= self.lengths.pop_front().unwrap_or(0);
+        }
+    }
+
+    /// The total length of the last `AVERAGE_WINDOW` counted tests, divided by
+    /// the size of the window even while there are fewer tests than that.
+    pub fn average_len(&self) -> usize {
+        self.sum_average_window / AVERAGE_WINDOW
+    }
+
+    /// Like `average_len`, over the last `RECENT_WINDOW` counted tests.
+    pub fn recent_average_len(&self) -> usize {
+        self.sum_recent_window / RECENT_WINDOW
+    }
+
+    pub fn recent_max_len(&self) -> Option<usize> {
+        self.lengths.iter().rev().take(RECENT_WINDOW).max().copied()
+    }
+
+    /// The number of counted tests which have failed since the last success.
+    pub fn failure_streak(&self) -> usize {
+        self.failure_streak
+    }
+
+    /// The index of the first glyph which hasn't been typed successfully.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+/// An overview of the practice recorded for one book.
+pub struct Summary {
+    /// The length of the book in glyphs.
+    pub length: usize,
+    /// The index of the first glyph which hasn't been typed successfully.
+    pub position: usize,
+    pub tests: usize,
+    pub succeeded: usize,
+    pub average_wpm: f64,
+    pub best_wpm: f64,
+}
+
+impl Summary {
+    pub fn new(text: &str, tests: &[TestResult]) -> Self {
+        let typed = tests
+            .iter()
+            .filter(|t| t.len() > MIN_COUNTED_LEN)
+            .collect::<Vec<_>>();
+        let succeeded = typed.iter().filter(|t| t.succeeded).collect::<Vec<_>>();
+        let average_wpm = if succeeded.is_empty() {
+            0.0
+        } else {
+            succeeded.iter().map(|t| t.wpm()).sum::<f64>() / succeeded.len() as f64
+        };
+        Self {
+            length: GlyphString::new(text.to_string()).len(),
+            position: tests
+                .iter()
+                .rfind(|t| t.succeeded)
+                .map_or(0, |t| t.end_index),
+            tests: typed.len(),
+            succeeded: succeeded.len(),
+            average_wpm,
+            best_wpm: succeeded.iter().map(|t| t.wpm()).fold(0.0, f64::max),
+        }
+    }
+
+    pub fn percent_complete(&self) -> f64 {
+        if self.length == 0 {
+            100.0
+        } else {
+            self.position as f64 * 100.0 / self.length as f64
+        }
+    }
+}
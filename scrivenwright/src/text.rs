@@ -1,61 +1,53 @@
+use crate::app::AppResult;
+use crate::chapters;
+use crate::clock::{Clock, SystemClock};
 use crate::glyph_string::GlyphString;
+use crate::schema::Versioned;
+use crate::stats::RollingStats;
 use crate::text_wrapper::{Dir, TextWrapper};
-use chrono::{serde::ts_microseconds, DateTime, Utc};
+use chrono::{serde::ts_microseconds, DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cell::OnceCell;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 const STARTING_SAMPLE_SIZE: usize = 100;
 
+type SaveFn = dyn Fn(&Record) -> AppResult<()>;
+
 pub struct OpenText {
     pub text: GlyphString,
     pub test: Option<Test>,
     pub focused_glyph: usize,
     wrapper: TextWrapper,
-    test_log: Vec<TestResult>,
+    stats: RollingStats,
     keypress_log: Vec<KeyPress>,
-    save: Box<dyn Fn(TestResult, Vec<KeyPress>)>,
+    /// The pauses in the test in progress which have ended.
+    pauses: Vec<Pause>,
+    save: Box<SaveFn>,
+    /// Records waiting to be saved, oldest first.
+    unsaved: Vec<Record>,
+    save_error: Option<String>,
+    clock: Box<dyn Clock>,
+    /// The book's chapters, found the first time they're needed.
+    chapters: OnceCell<Vec<(usize, usize)>>,
 }
 
 impl OpenText {
     fn next_test(&mut self) {
-        let start_index = self
-            .test_log
-            .iter()
-            .rfind(|t| t.succeeded)
-            .map_or(0, |t| t.end_index);
+        let start_index = self.stats.position();
 
         if start_index >= self.text.len() {
             self.test = None;
             return;
         }
 
-        let avg_50 = self
-            .test_log
-            .iter()
-            .map(|t| t.end_index - t.start_index)
-            .filter(|&len| len > 5)
-            .rev()
-            .take(50)
-            .sum::<usize>()
-            / 50;
-        let max_10 = self
-            .test_log
-            .iter()
-            .map(|t| t.end_index - t.start_index)
-            .filter(|&len| len > 5)
-            .rev()
-            .take(10)
-            .max()
-            .unwrap_or(STARTING_SAMPLE_SIZE);
-        let best = usize::max(avg_50, max_10) + 5;
-
-        let wrong_num = self
-            .test_log
-            .iter()
-            .rev()
-            .take_while(|t| !t.succeeded)
-            .map(|t| t.end_index - t.start_index)
-            .filter(|&len| len > 5)
-            .count();
+        let best = usize::max(
+            self.stats.average_len(),
+            self.stats.recent_max_len().unwrap_or(STARTING_SAMPLE_SIZE),
+        ) + 5;
+        let wrong_num = self.stats.failure_streak();
 
         let mut remaining = best.saturating_sub(wrong_num * 5);
         let mut in_final_word = true;
@@ -83,13 +75,28 @@ impl OpenText {
             start_index,
             cur_char: 0,
             length: usize::min(len, self.text.len() - start_index),
-            start_time: Utc::now(),
+            start_time: self.clock.now(),
+            paused_since: None,
         })
     }
 
     pub fn new<Save>(text: String, test_log: Vec<TestResult>, save: Save) -> Self
     where
-        Save: Fn(TestResult, Vec<KeyPress>) -> () + 'static,
+        Save: Fn(&Record) -> AppResult<()> + 'static,
+    {
+        Self::with_clock(text, test_log, save, SystemClock)
+    }
+
+    /// Like `new`, but takes its timestamps from `clock`.
+    pub fn with_clock<Save, C>(
+        text: String,
+        test_log: Vec<TestResult>,
+        save: Save,
+        clock: C,
+    ) -> Self
+    where
+        Save: Fn(&Record) -> AppResult<()> + 'static,
+        C: Clock + 'static,
     {
         let text = GlyphString::new(text);
         let mut ret = Self {
@@ -97,9 +104,14 @@ impl OpenText {
             text,
             test: None,
             wrapper: TextWrapper::new(),
-            test_log,
+            stats: RollingStats::new(&test_log),
             save: Box::new(save),
             keypress_log: Default::default(),
+            pauses: Vec::new(),
+            unsaved: Vec::new(),
+            save_error: None,
+            clock: Box::new(clock),
+            chapters: OnceCell::new(),
         };
         ret.next_test();
         ret.snap_to_cursor();
@@ -110,57 +122,152 @@ impl OpenText {
         if let Some(t) = &self.test {
             self.focused_glyph = t.start_index + t.cur_char;
         } else {
-            //panic!("{:?} {:?}", self.text.string, self.text.len());
-            self.focused_glyph = self.text.len() - 1
+            self.focused_glyph = self.text.len().saturating_sub(1)
         }
     }
 
     pub fn get_rolling_average(&self) -> usize {
-        self.test_log
-            .iter()
-            .map(|t| t.end_index - t.start_index)
-            .filter(|&len| len > 5)
-            .rev()
-            .take(10)
-            .sum::<usize>()
-            / 10
+        self.stats.recent_average_len()
     }
 
     pub fn handle_char(&mut self, c: char) {
+        self.handle_glyph(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Handles text which was entered all at once, such as a string committed
+    /// by an input method or a paste. It is typed glyph by glyph, and anything
+    /// after a mistake is dropped, as it was meant for the passage which has
+    /// to be typed again.
+    pub fn handle_input(&mut self, input: &str) {
+        for glyph in input.graphemes(true) {
+            if self.test.is_none() || !self.handle_glyph(glyph) {
+                return;
+            }
+        }
+    }
+
+    /// Returns whether the glyph was typed correctly.
+    fn handle_glyph(&mut self, glyph: &str) -> bool {
+        let mut correct = false;
         if let Some(test) = self.test.as_mut() {
-            let correct = c.to_string() == self.text[test.start_index + test.cur_char];
+            correct = same_glyph(glyph, &self.text[test.start_index + test.cur_char]);
+            let now = self.clock.now();
+            if let Some(since) = test.paused_since.take() {
+                self.pauses.push(Pause {
+                    start: since,
+                    end: now,
+                });
+            }
 
             if correct {
                 if test.cur_char == 0 {
-                    test.start_time = Utc::now();
+                    test.start_time = now;
                 }
                 test.cur_char += 1
             }
+
+            let log_entry = KeyPress {
+                correct,
+                key: glyph.to_string(),
+                time: now,
+            };
+            self.keypress_log.push(log_entry);
+
             if !correct || test.cur_char == test.length {
                 let res = TestResult {
                     succeeded: correct,
                     start_index: test.start_index,
                     end_index: test.start_index + test.cur_char,
                     started: test.start_time,
-                    completed: Utc::now(),
+                    completed: now,
+                    pauses: std::mem::take(&mut self.pauses),
                 };
-                self.test_log.push(res.clone());
-                (self.save)(res, self.keypress_log.clone());
-                self.keypress_log = Vec::new();
+                self.stats.push(&res);
+                let keypresses = std::mem::take(&mut self.keypress_log);
+                self.unsaved.push(Record::Test(res));
+                self.unsaved.push(Record::KeyPresses(keypresses));
+                self.retry_saves();
                 self.next_test();
             }
 
-            let log_entry = KeyPress {
-                correct,
-                key: c,
-                time: Utc::now(),
-            };
-            self.keypress_log.push(log_entry);
             self.snap_to_cursor();
         }
+        correct
+    }
+
+    /// Moves the next test to start at `index`, dropping the test in progress.
+    /// This is saved as a bookmark, so it is kept when the book is reopened.
+    pub fn move_to(&mut self, index: usize) {
+        let res = self.move_without_saving(index);
+        self.unsaved.push(Record::Test(res));
+        self.retry_saves();
+    }
+
+    /// Like `move_to`, but the book reopens where it was before, as the move
+    /// isn't saved. Returns the bookmark which would have been saved.
+    pub fn move_without_saving(&mut self, index: usize) -> TestResult {
+        let res = TestResult::bookmark(index.min(self.text.len()), self.clock.now());
+        self.keypress_log.clear();
+        self.pauses.clear();
+        self.stats.push(&res);
+        self.next_test();
+        self.snap_to_cursor();
+        res
+    }
+
+    /// The chapter headings of the book, as in [`chapters::chapters`].
+    pub fn chapters(&self) -> &[(usize, usize)] {
+        self.chapters.get_or_init(|| chapters::chapters(&self.text))
+    }
+
+    /// Pauses the test in progress once nothing has been typed for `after`.
+    /// The next keypress ends the pause, and the time from `after` past the
+    /// last keypress until then isn't counted in the test's duration.
+    pub fn pause_if_idle(&mut self, after: Duration) {
+        let (Some(test), Some(last)) = (self.test.as_mut(), self.keypress_log.last()) else {
+            return;
+        };
+        if test.paused_since.is_none() && self.clock.now() - last.time >= after {
+            test.paused_since = Some(last.time + after);
+        }
     }
 
-    pub fn lines<'a>(&'a self, line_width: u16, first: usize, num: usize) -> Vec<(usize, usize)> {
+    pub fn is_paused(&self) -> bool {
+        self.test.is_some_and(|t| t.paused_since.is_some())
+    }
+
+    /// Saves records which haven't been saved yet, oldest first. Returns
+    /// whether everything has now been saved.
+    pub fn retry_saves(&mut self) -> bool {
+        while let Some(record) = self.unsaved.first() {
+            if let Err(e) = (self.save)(record) {
+                self.save_error = Some(format!("Failed to save progress: {}", e));
+                return false;
+            }
+            self.unsaved.remove(0);
+        }
+        self.save_error = None;
+        true
+    }
+
+    /// Queues a record again when saving it failed after the save function
+    /// returned, as it does when saving in the background. It is saved
+    /// before anything still waiting, as it was made before them.
+    pub fn requeue(&mut self, record: Record, error: &str) {
+        self.unsaved.insert(0, record);
+        self.save_error = Some(format!("Failed to save progress: {}", error));
+    }
+
+    /// Records which couldn't be saved.
+    pub fn unsaved(&self) -> &[Record] {
+        &self.unsaved
+    }
+
+    pub fn save_error(&self) -> Option<&str> {
+        self.save_error.as_deref()
+    }
+
+    pub fn lines(&self, line_width: u16, first: usize, num: usize) -> Vec<(usize, usize)> {
         self.wrapper.lines(&self.text, line_width, first, num)
     }
 
@@ -178,29 +285,118 @@ impl OpenText {
     }
 }
 
+/// Compares glyphs in the same normal form, so that an accented letter typed
+/// as one character matches the letter and a combining accent in the book.
+fn same_glyph(typed: &str, expected: &str) -> bool {
+    typed == expected || typed.nfc().eq(expected.nfc())
+}
+
+/// Something for the frontend to save. A test is saved before its
+/// keypresses, and each is only saved once, so a failure part way through
+/// doesn't save the test again when it is retried.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Record {
+    Test(TestResult),
+    KeyPresses(Vec<KeyPress>),
+}
+
 #[derive(Clone, Copy)]
 pub struct Test {
     pub start_index: usize,
     pub length: usize,
     pub cur_char: usize,
     start_time: DateTime<Utc>,
+    paused_since: Option<DateTime<Utc>>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct KeyPress {
-    correct: bool,
-    key: char,
+    pub correct: bool,
+    /// The glyph which was typed. This was a single `char` before input
+    /// methods were supported, which is written the same way.
+    pub key: String,
     #[serde(with = "ts_microseconds")]
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct TestResult {
-    succeeded: bool,
-    start_index: usize,
-    end_index: usize,
+    pub succeeded: bool,
+    pub start_index: usize,
+    pub end_index: usize,
+    #[serde(with = "ts_microseconds")]
+    pub started: DateTime<Utc>,
     #[serde(with = "ts_microseconds")]
-    started: DateTime<Utc>,
+    pub completed: DateTime<Utc>,
+    /// Times when the typist was idle, which aren't counted in the duration.
+    pub pauses: Vec<Pause>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Pause {
+    #[serde(with = "ts_microseconds")]
+    pub start: DateTime<Utc>,
     #[serde(with = "ts_microseconds")]
-    completed: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TestResult {
+    /// A zero length test which moves the start of the next test to `index`.
+    pub fn bookmark(index: usize, time: DateTime<Utc>) -> Self {
+        Self {
+            succeeded: true,
+            start_index: index,
+            end_index: index,
+            started: time,
+            completed: time,
+            pauses: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end_index.saturating_sub(self.start_index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The time spent typing, not counting pauses.
+    pub fn duration_secs(&self) -> f64 {
+        let paused = self
+            .pauses
+            .iter()
+            .fold(Duration::zero(), |total, p| total + (p.end - p.start));
+        (self.completed - self.started - paused)
+            .num_microseconds()
+            .unwrap_or(0)
+            .max(0) as f64
+            / 1_000_000.0
+    }
+
+    /// Words per minute, counting five glyphs as a word.
+    pub fn wpm(&self) -> f64 {
+        let secs = self.duration_secs();
+        if secs > 0.0 {
+            self.len() as f64 / 5.0 / (secs / 60.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn v0_to_v1(_: &mut Map<String, Value>) {}
+
+fn test_result_v1_to_v2(map: &mut Map<String, Value>) {
+    map.insert("pauses".into(), Value::Array(Vec::new()));
+}
+
+impl Versioned for KeyPress {
+    const VERSION: u32 = 1;
+    const MIGRATIONS: &'static [fn(&mut Map<String, Value>)] = &[v0_to_v1];
+}
+
+impl Versioned for TestResult {
+    const VERSION: u32 = 2;
+    const MIGRATIONS: &'static [fn(&mut Map<String, Value>)] = &[v0_to_v1, test_result_v1_to_v2];
 }
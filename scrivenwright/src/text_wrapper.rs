@@ -1,5 +1,4 @@
 use crate::glyph_string::GlyphString;
-use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::{max, min};
 use textwrap::Options;
@@ -28,9 +27,9 @@ impl TextWrapper {
         f(inner)
     }
 
-    pub fn lines<'a>(
+    pub fn lines(
         &self,
-        text: &'a GlyphString,
+        text: &GlyphString,
         line_width: u16,
         first: usize,
         num: usize,
@@ -62,13 +61,19 @@ impl TextWrapper {
     }
 }
 
+impl Default for TextWrapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug)]
 struct Inner {
     cur_width: u16,
     lines: Box<[(usize, usize)]>,
 }
 
-pub(crate) enum Dir {
+pub enum Dir {
     Up,
     Down,
     Left,
@@ -76,32 +81,44 @@ pub(crate) enum Dir {
 }
 
 impl Inner {
+    /// Wraps `text`, producing lines which exactly tile its glyphs. Each line
+    /// runs from the end of the previous line up to the end of the text that
+    /// textwrap put on it, so whitespace which textwrap drops between lines
+    /// stays in the text, and a line never ends inside a glyph.
     fn new(text: &GlyphString, cur_width: u16) -> Inner {
         let options: Options = Options::new(cur_width as usize)
             .break_words(true)
             .word_splitter(textwrap::WordSplitter::HyphenSplitter)
             .preserve_trailing_space(true);
+        let mut lines = Vec::new();
+        let mut start = 0;
         let mut wrapped_lines = Vec::new();
-        for line in text.string.split_inclusive('\n') {
+        for line in text.as_str().split_inclusive('\n') {
+            let line_start = line.as_ptr() as usize - text.as_str().as_ptr() as usize;
+            let line_end = line_start + line.len();
+            wrapped_lines.clear();
             textwrap::wrap_single_line(line, &options, &mut wrapped_lines);
+
+            let mut pos = line_start;
+            for (i, cow) in wrapped_lines.iter().enumerate() {
+                let end = if i + 1 == wrapped_lines.len() {
+                    line_end
+                } else {
+                    end_in(text.as_str(), pos, cow)
+                };
+                let end_glyph = text.index_of_ptr(end);
+                if end_glyph > start {
+                    lines.push((start, end_glyph));
+                    start = end_glyph;
+                }
+                pos = max(pos, end);
+            }
         }
-        let base = text.string.as_ptr() as usize;
 
-        let inner = Inner {
+        Inner {
             cur_width,
-            lines: wrapped_lines
-                .iter()
-                .map(|cow| match cow {
-                    Cow::Borrowed(s) => {
-                        let start = s.as_ptr() as usize - base;
-                        let end = start + s.len();
-                        (text.index_of_ptr(start), text.index_of_ptr(end))
-                    }
-                    Cow::Owned(_) => panic! {"Jesse has misunderstood the textwrap library."},
-                })
-                .collect(),
-        };
-        inner
+            lines: lines.into(),
+        }
     }
 
     fn wrap(&mut self, text: &GlyphString, line_width: u16) {
@@ -111,16 +128,16 @@ impl Inner {
         *self = Inner::new(text, line_width);
     }
 
-    fn lines<'a>(
+    fn lines(
         &mut self,
-        text: &'a GlyphString,
+        text: &GlyphString,
         line_width: u16,
         first: usize,
         num: usize,
     ) -> Vec<(usize, usize)> {
         self.wrap(text, line_width);
         let mut ret = Vec::new();
-        for i in first..min(self.lines.len(), first + num) {
+        for i in first..min(self.lines.len(), first.saturating_add(num)) {
             ret.push((self.lines[i].0, self.lines[i].1));
         }
         ret
@@ -158,7 +175,29 @@ impl Inner {
         self.wrap(text, line_width);
         match self.lines.binary_search_by_key(&idx, |&(start, _)| start) {
             Ok(l) => Some((l, 0)),
-            Err(l) => Some((l - 1, idx - self.lines[l - 1].0)),
+            Err(l) => {
+                let l = l.checked_sub(1)?;
+                let (start, end) = self.lines[l];
+                (idx < end).then_some((l, idx - start))
+            }
         }
     }
 }
+
+/// The byte offset in `string` where a line produced by textwrap ends. Lines
+/// are normally borrowed from `string`, but textwrap can also return owned or
+/// static lines, which are matched against the text following `pos` instead.
+fn end_in(string: &str, pos: usize, line: &str) -> usize {
+    let base = string.as_ptr() as usize;
+    let ptr = line.as_ptr() as usize;
+    if ptr >= base && ptr + line.len() <= base + string.len() {
+        return ptr - base + line.len();
+    }
+    let matched = string[pos..]
+        .char_indices()
+        .zip(line.chars())
+        .take_while(|&((_, a), b)| a == b)
+        .last()
+        .map_or(0, |((i, c), _)| i + c.len_utf8());
+    pos + matched
+}
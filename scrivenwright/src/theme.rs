@@ -0,0 +1,185 @@
+use crate::app::AppResult;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+pub const BUILTIN_THEMES: &[&str] = &["dark", "light", "solarized", "high-contrast"];
+
+/// The colours used to draw the text and interface. Colours are written as
+/// names like `"blue"` or `"dark gray"`, `"#RRGGBB"`, or a 256 colour palette
+/// index like `"208"`. `"reset"` uses the terminal's own colour.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(with = "color")]
+    pub background: Color,
+    /// Text outside of the current test.
+    #[serde(with = "color")]
+    pub out_of_test: Color,
+    /// Text in the current test which hasn't been typed yet.
+    #[serde(with = "color")]
+    pub untyped: Color,
+    #[serde(with = "color")]
+    pub typed: Color,
+    /// The next glyph to type.
+    pub current: Highlight,
+    /// The glyph which has been moved to with the arrow keys.
+    pub focus: Highlight,
+    pub error: Highlight,
+    #[serde(with = "color")]
+    pub border: Color,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Highlight {
+    #[serde(with = "color")]
+    pub fg: Color,
+    #[serde(with = "color")]
+    pub bg: Color,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            background: Color::Reset,
+            out_of_test: Color::DarkGray,
+            untyped: Color::Blue,
+            typed: Color::White,
+            current: Highlight::new(Color::Black, Color::Blue),
+            focus: Highlight::new(Color::Black, Color::White),
+            error: Highlight::new(Color::White, Color::Red),
+            border: Color::White,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            background: Color::Rgb(0xFA, 0xFA, 0xFA),
+            out_of_test: Color::Rgb(0xA0, 0xA0, 0xA0),
+            untyped: Color::Rgb(0x1E, 0x64, 0xC8),
+            typed: Color::Rgb(0x20, 0x20, 0x20),
+            current: Highlight::new(Color::Rgb(0xFA, 0xFA, 0xFA), Color::Rgb(0x1E, 0x64, 0xC8)),
+            focus: Highlight::new(Color::Rgb(0xFA, 0xFA, 0xFA), Color::Rgb(0x20, 0x20, 0x20)),
+            error: Highlight::new(Color::Rgb(0xFF, 0xFF, 0xFF), Color::Rgb(0xC6, 0x28, 0x28)),
+            border: Color::Rgb(0x20, 0x20, 0x20),
+        }
+    }
+
+    pub fn solarized() -> Self {
+        let base03 = Color::Rgb(0x00, 0x2B, 0x36);
+        let base01 = Color::Rgb(0x58, 0x6E, 0x75);
+        let base0 = Color::Rgb(0x83, 0x94, 0x96);
+        let base1 = Color::Rgb(0x93, 0xA1, 0xA1);
+        let base2 = Color::Rgb(0xEE, 0xE8, 0xD5);
+        let base3 = Color::Rgb(0xFD, 0xF6, 0xE3);
+        let blue = Color::Rgb(0x26, 0x8B, 0xD2);
+        let red = Color::Rgb(0xDC, 0x32, 0x2F);
+        Self {
+            background: base03,
+            out_of_test: base01,
+            untyped: blue,
+            typed: base1,
+            current: Highlight::new(base03, blue),
+            focus: Highlight::new(base03, base2),
+            error: Highlight::new(base3, red),
+            border: base0,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            background: Color::Black,
+            out_of_test: Color::Gray,
+            untyped: Color::LightYellow,
+            typed: Color::White,
+            current: Highlight::new(Color::Black, Color::LightYellow),
+            focus: Highlight::new(Color::Black, Color::White),
+            error: Highlight::new(Color::White, Color::Red),
+            border: Color::White,
+        }
+    }
+
+    /// One of `BUILTIN_THEMES`.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "solarized" => Some(Self::solarized()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// The name of the built-in theme which this is, if it is one.
+    pub fn builtin_name(&self) -> Option<&'static str> {
+        BUILTIN_THEMES
+            .iter()
+            .copied()
+            .find(|&name| Self::builtin(name).as_ref() == Some(self))
+    }
+
+    /// Reads a theme from TOML. Colours which aren't given are taken from the
+    /// dark theme, or from the built-in theme named by `base`.
+    pub fn from_toml(toml: &str) -> AppResult<Self> {
+        #[derive(Deserialize)]
+        struct Base {
+            base: Option<String>,
+        }
+        let table: toml::Table = toml::from_str(toml)?;
+        let base = match table.clone().try_into::<Base>()?.base {
+            Some(name) => {
+                Self::builtin(&name).ok_or_else(|| format!("There is no theme named {}", name))?
+            }
+            None => Self::default(),
+        };
+        let mut merged = toml::Table::try_from(base)?;
+        for (key, value) in table {
+            match (merged.get_mut(&key), value) {
+                (Some(toml::Value::Table(fields)), toml::Value::Table(overrides)) => {
+                    fields.extend(overrides)
+                }
+                (_, value) => {
+                    merged.insert(key, value);
+                }
+            }
+        }
+        merged.remove("base");
+        Ok(merged.try_into()?)
+    }
+
+    pub fn to_toml(&self) -> AppResult<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Highlight {
+    pub const fn new(fg: Color, bg: Color) -> Self {
+        Self { fg, bg }
+    }
+}
+
+/// Writes colours in the same format that ratatui parses them from.
+mod color {
+    use ratatui::style::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        color: &Color,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(color)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| D::Error::custom(format!("{} isn't a colour", s)))
+    }
+}
@@ -1,8 +1,8 @@
-use crate::app::{App, PlatformAdapter};
+use crate::app::{App, Inspection, Library, PlatformAdapter};
 use crate::text::OpenText;
 use ratatui::{
     layout::Alignment,
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
@@ -10,25 +10,117 @@ use ratatui::{prelude::*, widgets::*};
 
 impl<PA: PlatformAdapter> App<PA> {
     pub fn render(&self, text: &OpenText, frame: &mut Frame) {
-        let line_width = self
-            .settings
-            .line_width((frame.size().width).saturating_sub(2));
+        match &self.library {
+            Some(library) => self.render_library(library, frame),
+            None => self.render_text(text, frame),
+        }
+        if self.library.is_none() && text.is_paused() {
+            self.render_paused(frame);
+        }
+        if let (None, Some(inspected)) = (&self.library, &self.inspected) {
+            self.render_inspection(inspected, text, frame);
+        }
+
+        if let Some(error) = self.error.as_deref().or(text.save_error()) {
+            let screen = frame.size();
+            let banner = Rect::new(
+                1,
+                screen.height.saturating_sub(2),
+                screen.width.saturating_sub(2),
+                screen.height.min(1),
+            );
+            frame.render_widget(Clear, banner);
+            let theme = &self.settings.theme;
+            frame.render_widget(
+                Paragraph::new(error).style(Style::new().fg(theme.error.fg).bg(theme.error.bg)),
+                banner,
+            );
+        }
+    }
+
+    /// Where the lines of the book are drawn, so that clicks can be mapped
+    /// back to glyphs.
+    fn text_layout(&self, text: &OpenText, screen: Rect) -> TextLayout {
+        let line_width = self.settings.line_width(screen.width.saturating_sub(2));
+        // Only an empty book has no line to focus.
         let cur_line = text
             .line_offset_of_idx(text.focused_glyph, line_width)
-            .expect("Focused glyph outside")
-            .0;
-        let num_rows = (frame.size().height as usize).saturating_sub(2);
+            .map_or(0, |(line, _)| line);
+        let num_rows = (screen.height as usize).saturating_sub(2);
         let rows_to_center = (num_rows / 2).saturating_sub(2);
         let first_line = cur_line.saturating_sub(rows_to_center);
         let first_row = rows_to_center.saturating_sub(cur_line);
         let num_lines = num_rows - first_row;
 
+        let vert = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(first_row as u16 + 1),
+                Constraint::Percentage(100),
+            ])
+            .split(screen);
+        let area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage((100 - self.settings.text_width_percent) / 2),
+                Constraint::Percentage(self.settings.text_width_percent),
+                Constraint::Percentage((100 - self.settings.text_width_percent) / 2),
+            ])
+            .split(vert[1])[1];
+        TextLayout {
+            area,
+            first_line,
+            num_lines,
+            line_width,
+        }
+    }
+
+    /// The index of the glyph drawn at a cell of the screen.
+    pub(crate) fn glyph_at(
+        &self,
+        text: &OpenText,
+        screen: Rect,
+        column: u16,
+        row: u16,
+    ) -> Option<usize> {
+        let layout = self.text_layout(text, screen);
+        let area = layout.area;
+        if column < area.x || row < area.y || column >= area.right() || row >= area.bottom() {
+            return None;
+        }
+        let line = (row - area.y) as usize;
+        if line >= layout.num_lines {
+            return None;
+        }
+        let &(sidx, eidx) = text
+            .lines(layout.line_width, layout.first_line + line, 1)
+            .first()?;
+        let mut x = area.x;
+        for (idx, c) in text.text.glyphs().enumerate().skip(sidx).take(eidx - sidx) {
+            // Line breaks are drawn as one cell, like in `render_text`.
+            x += if c == "\n" {
+                1
+            } else {
+                Span::raw(c).width() as u16
+            };
+            if column < x {
+                return Some(idx);
+            }
+        }
+        None
+    }
+
+    fn render_text(&self, text: &OpenText, frame: &mut Frame) {
+        let screen = Rect::new(0, 0, frame.size().width, frame.size().height);
+        let layout = self.text_layout(text, screen);
+
         let sidx = text
             .test
             .map(|t| t.start_index)
             .unwrap_or(text.focused_glyph);
         let cidx = sidx + text.test.map(|t| t.cur_char).unwrap_or(0);
         let eidx = sidx + text.test.map(|t| t.length).unwrap_or(0);
+        let theme = &self.settings.theme;
         let style_char = |idx: usize, c: &str| -> Span {
             let mut s: Span<'_>;
             if c == "\n" && idx >= sidx && idx < eidx {
@@ -40,23 +132,23 @@ impl<PA: PlatformAdapter> App<PA> {
             }
 
             s = if idx < sidx || idx >= eidx {
-                s.dark_gray()
+                s.fg(theme.out_of_test)
             } else if idx < cidx {
-                s.white()
+                s.fg(theme.typed)
             } else if idx == cidx {
-                s.black().bg(Color::Blue)
+                s.fg(theme.current.fg).bg(theme.current.bg)
             } else {
-                s.blue()
+                s.fg(theme.untyped)
             };
             if idx == text.focused_glyph {
-                s.black().bg(Color::White)
+                s.fg(theme.focus.fg).bg(theme.focus.bg)
             } else {
                 s
             }
         };
 
         let display_lines: Vec<Line> = text
-            .lines(line_width, first_line, num_lines)
+            .lines(layout.line_width, layout.first_line, layout.num_lines)
             .iter()
             .map(|&(sidx, eidx)| {
                 let styled = text
@@ -71,28 +163,15 @@ impl<PA: PlatformAdapter> App<PA> {
             })
             .collect();
 
-        let graph = Paragraph::new::<Text>(display_lines.into()).style(Style::default());
-
-        let screen = Rect::new(0, 0, frame.size().width, frame.size().height);
-
-        let vert = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(first_row as u16 + 1),
-                Constraint::Percentage(100),
-            ])
-            .split(screen);
-        let horiz = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage((100 - self.settings.text_width_percent) / 2),
-                Constraint::Percentage(self.settings.text_width_percent),
-                Constraint::Percentage((100 - self.settings.text_width_percent) / 2),
-            ])
-            .split(vert[1])[1];
+        let graph =
+            Paragraph::new::<Text>(display_lines.into()).style(Style::new().bg(theme.background));
 
         // Render into the second chunk of the layout.
-        frame.render_widget(graph, horiz);
+        frame.render_widget(
+            Block::new().style(Style::new().bg(theme.background)),
+            screen,
+        );
+        frame.render_widget(graph, layout.area);
         frame.render_widget(
             Block::default()
                 .title("Scrivenwright")
@@ -101,8 +180,145 @@ impl<PA: PlatformAdapter> App<PA> {
                         .alignment(Alignment::Right),
                 )
                 .borders(Borders::ALL)
-                .border_style(Style::new().white()),
+                .border_style(Style::new().fg(theme.border)),
             screen,
         );
     }
+
+    fn render_paused(&self, frame: &mut Frame) {
+        let theme = &self.settings.theme;
+        let screen = frame.size();
+        let message = "Type to continue";
+        let (width, height) = (message.len() as u16 + 4, 3);
+        let overlay = Rect::new(
+            screen.width.saturating_sub(width) / 2,
+            screen.height.saturating_sub(height) / 2,
+            width.min(screen.width),
+            height.min(screen.height),
+        );
+        frame.render_widget(Clear, overlay);
+        frame.render_widget(
+            Paragraph::new(message)
+                .alignment(Alignment::Center)
+                .style(Style::new().fg(theme.untyped).bg(theme.background))
+                .block(
+                    Block::default()
+                        .title("Paused")
+                        .borders(Borders::ALL)
+                        .border_style(Style::new().fg(theme.border)),
+                ),
+            overlay,
+        );
+    }
+
+    fn render_inspection(&self, inspected: &Inspection, text: &OpenText, frame: &mut Frame) {
+        let theme = &self.settings.theme;
+        let screen = frame.size();
+        let glyph = match text.text.glyphs().nth(inspected.index) {
+            Some("\n") => "line break".to_string(),
+            Some(" ") => "space".to_string(),
+            Some(c) => format!("\"{}\"", c),
+            None => return,
+        };
+        let typed = text.test.is_none_or(|t| inspected.index < t.start_index);
+        let mut lines = vec![
+            format!("Glyph     {}", glyph),
+            format!(
+                "Position  {} of {} ({:.2}%)",
+                inspected.index + 1,
+                text.text.len(),
+                (inspected.index + 1) as f64 * 100.0 / text.text.len() as f64
+            ),
+        ];
+        if let Some(chapter) = inspected.chapter {
+            lines.push(format!("Chapter   {}", chapter));
+        }
+        lines.push(format!(
+            "Typed     {}",
+            if typed { "yes" } else { "not yet" }
+        ));
+
+        let hint = "Enter to start here, Esc to close";
+        let width = lines
+            .iter()
+            .map(|l| Span::raw(l.as_str()).width())
+            .chain([hint.len()])
+            .max()
+            .unwrap_or(0) as u16
+            + 4;
+        let height = lines.len() as u16 + 2;
+        // Above the error banner, so that both can be read.
+        let popup = Rect::new(
+            screen.width.saturating_sub(width) / 2,
+            screen.height.saturating_sub(height + 2),
+            width.min(screen.width),
+            height.min(screen.height),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+                .style(Style::new().fg(theme.untyped).bg(theme.background))
+                .block(
+                    Block::default()
+                        .title(block::Title::from(hint).position(block::Position::Bottom))
+                        .borders(Borders::ALL)
+                        .border_style(Style::new().fg(theme.border))
+                        .padding(Padding::horizontal(1)),
+                ),
+            popup,
+        );
+    }
+
+    fn render_library(&self, library: &Library, frame: &mut Frame) {
+        let theme = &self.settings.theme;
+        let screen = frame.size();
+        let items = library
+            .texts
+            .iter()
+            .map(|t| {
+                let progress = match t.percent_complete() {
+                    Some(percent) => format!("{:>6.2}%", percent),
+                    None => " sample".to_string(),
+                };
+                let mut line = vec![
+                    Span::raw(format!("{}  ", progress)).fg(theme.out_of_test),
+                    Span::raw(t.name.clone()).fg(theme.untyped),
+                ];
+                if let Some(author) = &t.author {
+                    line.push(Span::raw(format!(" by {}", author)).fg(theme.out_of_test));
+                }
+                if t.chapters > 0 {
+                    line.push(
+                        Span::raw(format!(" ({} chapters)", t.chapters)).fg(theme.out_of_test),
+                    );
+                }
+                ListItem::new(Line::from(line))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title("Library")
+                    .title(
+                        block::Title::from("Enter to open, Esc to go back")
+                            .alignment(Alignment::Right),
+                    )
+                    .borders(Borders::ALL)
+                    .border_style(Style::new().fg(theme.border)),
+            )
+            .style(Style::new().bg(theme.background))
+            .highlight_style(Style::new().fg(theme.current.fg).bg(theme.current.bg));
+        let rows = screen.height.saturating_sub(2) as usize;
+        let mut state = ListState::default()
+            .with_offset(library.offset(rows))
+            .with_selected(Some(library.selected));
+        frame.render_stateful_widget(list, screen, &mut state);
+    }
+}
+
+struct TextLayout {
+    area: Rect,
+    first_line: usize,
+    num_lines: usize,
+    line_width: u16,
 }
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use scrivenwright::bundle::{merge_logs, BookBundle};
+use scrivenwright::clock::ManualClock;
+use scrivenwright::text::{KeyPress, OpenText, Record, TestResult};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Saved = Rc<RefCell<Vec<(TestResult, Vec<KeyPress>)>>>;
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+/// Pastes `input` into a book of repeated words, and returns the tests and
+/// keypresses which were saved. Everything is recorded at the same time.
+fn paste(input: &str) -> (Vec<TestResult>, Vec<KeyPress>) {
+    let saved = Saved::default();
+    let sink = saved.clone();
+    let mut text = OpenText::with_clock(
+        "word ".repeat(200),
+        Vec::new(),
+        move |record: &Record| {
+            let mut saved = sink.borrow_mut();
+            match record {
+                Record::Test(test) => saved.push((test.clone(), Vec::new())),
+                Record::KeyPresses(keypresses) => {
+                    saved.last_mut().unwrap().1.extend_from_slice(keypresses)
+                }
+            }
+            Ok(())
+        },
+        ManualClock::new(start()),
+    );
+    text.handle_input(input);
+    let saved = saved.take();
+    let tests = saved.iter().map(|(t, _)| t.clone()).collect();
+    let keypresses = saved.into_iter().flat_map(|(_, k)| k).collect();
+    (tests, keypresses)
+}
+
+fn keypress(key: &str, secs: i64) -> KeyPress {
+    KeyPress {
+        correct: true,
+        key: key.to_string(),
+        time: start() + Duration::seconds(secs),
+    }
+}
+
+#[test]
+fn same_instant_keypresses_are_kept() {
+    let (_, keypresses) = paste(&"word ".repeat(30));
+    assert_eq!(keypresses.len(), 110);
+    assert_eq!(merge_logs(&keypresses, &[]), keypresses);
+    assert_eq!(merge_logs(&[], &keypresses), keypresses);
+    assert_eq!(merge_logs(&keypresses, &keypresses), keypresses);
+}
+
+#[test]
+fn a_paste_which_completes_two_tests_keeps_both() {
+    let (tests, _) = paste(&"word ".repeat(60));
+    assert_eq!(tests.len(), 2);
+    assert_eq!(tests[0].completed, tests[1].completed);
+    assert_eq!(merge_logs(&tests, &[]), tests);
+    assert_eq!(merge_logs(&tests, &tests), tests);
+
+    // Merging into a device which only saw the first test adds the second.
+    let merged = merge_logs(&tests[..1], &tests);
+    assert_eq!(merged, tests);
+}
+
+#[test]
+fn records_in_both_logs_are_kept_once() {
+    let ours = vec![keypress("a", 0), keypress("b", 1), keypress("b", 1)];
+    let theirs = vec![keypress("b", 1), keypress("c", 1), keypress("d", 2)];
+    let merged = merge_logs(&ours, &theirs);
+    let keys = merged.iter().map(|k| k.key.as_str()).collect::<Vec<_>>();
+    // Only one of our two "b"s is matched by theirs.
+    assert_eq!(keys, ["a", "b", "b", "c", "d"]);
+}
+
+#[test]
+fn merging_into_a_different_text_fails() {
+    let (tests, keypresses) = paste("word ");
+    let book = BookBundle::new("words".into(), "other".into(), tests, keypresses);
+    let (mut tests, mut keypresses) = (Vec::new(), Vec::new());
+    assert!(book
+        .merge_into(Some("words"), &mut tests, &mut keypresses)
+        .is_err());
+    assert!(tests.is_empty());
+}
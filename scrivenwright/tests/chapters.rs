@@ -0,0 +1,57 @@
+use scrivenwright::chapters::{chapter_at, chapter_start, chapters};
+use scrivenwright::glyph_string::GlyphString;
+
+fn book(text: &str) -> GlyphString {
+    GlyphString::new(text.to_string())
+}
+
+#[test]
+fn headings_start_paragraphs() {
+    let text = book("Chapter 1\n\nIt was a dark night.\n\nCHAPTER 2. Dawn\n\nThe chapter 3 ended.");
+    assert_eq!(chapters(&text), vec![(1, 0), (2, 33)]);
+}
+
+#[test]
+fn roman_numerals_are_numbers() {
+    let text = book("Chapter I\n\nOne.\n\nChapter IV\n\nFour.\n\nchapter xix\n\nNineteen.");
+    let numbers = chapters(&text).iter().map(|&(n, _)| n).collect::<Vec<_>>();
+    assert_eq!(numbers, vec![1, 4, 19]);
+}
+
+#[test]
+fn headings_are_on_one_line() {
+    let text = book("Chapter\nI went home.\n\nChapter 2\r\n\nTwo.");
+    assert_eq!(chapters(&text), vec![(2, 22)]);
+}
+
+#[test]
+fn words_made_of_numeral_letters_arent_numbers() {
+    let text =
+        book("Chapter mix of things\n\nChapter civil war\n\nChapter dim.\n\nChapter xii: Twelve");
+    let numbers = chapters(&text).iter().map(|&(n, _)| n).collect::<Vec<_>>();
+    assert_eq!(numbers, vec![12]);
+}
+
+#[test]
+fn table_of_contents_is_skipped() {
+    let text = book("Contents\n\nChapter 1\n\nChapter 2\n\nChapter 1\n\nOne.\n\nChapter 2\n\nTwo.");
+    assert_eq!(chapters(&text), vec![(1, 32), (2, 49)]);
+    assert_eq!(chapter_start(&chapters(&text), 2), Some(49));
+    assert_eq!(chapter_start(&chapters(&text), 3), None);
+}
+
+#[test]
+fn indices_count_glyphs() {
+    let text = book("Caf\u{e9} e\u{301}\n\nChapter 1\n\nOne.");
+    assert_eq!(chapter_start(&chapters(&text), 1), Some(8));
+}
+
+#[test]
+fn glyphs_are_in_the_chapter_before_them() {
+    let text = book("Preface.\n\nChapter 1\n\nOne.\n\nChapter 2\n\nTwo.");
+    let chapters = chapters(&text);
+    assert_eq!(chapter_at(&chapters, 0), None);
+    assert_eq!(chapter_at(&chapters, 10), Some(1));
+    assert_eq!(chapter_at(&chapters, 26), Some(1));
+    assert_eq!(chapter_at(&chapters, 27), Some(2));
+}
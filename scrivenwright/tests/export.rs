@@ -0,0 +1,123 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use scrivenwright::export::{self, Format, History};
+use scrivenwright::text::{KeyPress, Pause, TestResult};
+use serde_json::Value;
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() + Duration::seconds(secs)
+}
+
+fn keypress(key: &str, correct: bool, secs: i64) -> KeyPress {
+    KeyPress {
+        correct,
+        key: key.to_string(),
+        time: at(secs),
+    }
+}
+
+/// Ten glyphs typed in 12 seconds, with a 2 second pause, and one mistake in
+/// four keypresses.
+fn history() -> (Vec<TestResult>, Vec<KeyPress>) {
+    let test = TestResult {
+        succeeded: true,
+        start_index: 0,
+        end_index: 10,
+        started: at(0),
+        completed: at(12),
+        pauses: vec![Pause {
+            start: at(5),
+            end: at(7),
+        }],
+    };
+    let keypresses = vec![
+        keypress("\"", true, 1),
+        keypress("x", false, 2),
+        keypress(",", true, 12),
+        keypress("a", true, 13),
+    ];
+    (vec![test], keypresses)
+}
+
+fn export(title: &str, text: &str, format: Format) -> (String, String) {
+    let (tests, keypresses) = history();
+    let books = [History {
+        title,
+        text,
+        tests: &tests,
+        keypresses: &keypresses,
+    }];
+    let mut out = Vec::new();
+    export::write_tests(&books, format, &mut out).unwrap();
+    let tests = String::from_utf8(out).unwrap();
+    let mut out = Vec::new();
+    export::write_keypresses(&books, format, &mut out).unwrap();
+    (tests, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn csv_fields_are_quoted_when_needed() {
+    let (tests, keypresses) = export("Emma, \"a novel\"", "a,\"b\"\ncd ef more", Format::Csv);
+    let mut lines = tests.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "book,succeeded,start_index,end_index,started,completed,duration_secs,wpm,accuracy,passage"
+    );
+    let row = tests.split_once('\n').unwrap().1;
+    assert!(row.starts_with("\"Emma, \"\"a novel\"\"\",true,0,10,"));
+    assert!(row.ends_with(",\"a,\"\"b\"\"\ncd e\"\n"));
+
+    let rows = keypresses.lines().collect::<Vec<_>>();
+    assert_eq!(rows[0], "book,time,key,correct");
+    assert!(rows[1].ends_with(",\"\"\"\",true"));
+    assert!(rows[3].ends_with(",\",\",true"));
+    assert_eq!(rows.len(), 5);
+}
+
+#[test]
+fn derived_columns_leave_out_pauses_and_other_tests_keypresses() {
+    let (tests, _) = export("Emma", "0123456789 and more", Format::Json);
+    let row: Value = serde_json::from_str(tests.lines().next().unwrap()).unwrap();
+    assert_eq!(row["duration_secs"], 10.0);
+    // Two words in ten seconds.
+    assert_eq!(row["wpm"], 12.0);
+    // The keypress after the test was completed isn't counted.
+    assert_eq!(row["accuracy"], 2.0 / 3.0);
+    assert_eq!(row["passage"], "0123456789");
+
+    let (csv, _) = export("Emma", "0123456789 and more", Format::Csv);
+    let fields = csv.lines().nth(1).unwrap().split(',').collect::<Vec<_>>();
+    assert_eq!(&fields[6..9], ["10", "12", &(2.0f64 / 3.0).to_string()]);
+}
+
+#[test]
+fn tests_without_keypresses_have_no_accuracy() {
+    let (tests, _) = history();
+    let books = [History {
+        title: "Emma",
+        text: "0123456789",
+        tests: &tests,
+        keypresses: &[],
+    }];
+    let mut out = Vec::new();
+    export::write_tests(&books, Format::Csv, &mut out).unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    assert!(csv.lines().nth(1).unwrap().ends_with(",10,12,,0123456789"));
+}
+
+#[test]
+fn bookmarks_are_left_out() {
+    let (mut tests, keypresses) = history();
+    tests.insert(0, TestResult::bookmark(3, at(-1)));
+    tests.push(TestResult::bookmark(10, at(20)));
+    let books = [History {
+        title: "Emma",
+        text: "0123456789",
+        tests: &tests,
+        keypresses: &keypresses,
+    }];
+    let mut out = Vec::new();
+    export::write_tests(&books, Format::Json, &mut out).unwrap();
+    let json = String::from_utf8(out).unwrap();
+    assert_eq!(json.lines().count(), 1);
+    assert!(json.contains(r#""end_index":10"#));
+}
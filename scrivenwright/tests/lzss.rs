@@ -0,0 +1,21 @@
+use proptest::prelude::*;
+use scrivenwright::lzss::{compress, decompress};
+
+proptest! {
+    #[test]
+    fn round_trips(input in proptest::collection::vec(any::<u8>(), 0..2000)) {
+        prop_assert_eq!(decompress(&compress(&input)), Some(input));
+    }
+
+    #[test]
+    fn round_trips_repetitive_text(word in "[a-c ]{1,8}", n in 0usize..500) {
+        let input = word.repeat(n).into_bytes();
+        prop_assert_eq!(decompress(&compress(&input)), Some(input));
+    }
+}
+
+#[test]
+fn text_gets_smaller() {
+    let text = "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife. ".repeat(20);
+    assert!(compress(text.as_bytes()).len() < text.len() / 4);
+}
@@ -0,0 +1,448 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use scrivenwright::app::App;
+use scrivenwright::clock::ManualClock;
+use scrivenwright::schema;
+use scrivenwright::text::{KeyPress, OpenText, Record, TestResult};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Saved = Rc<RefCell<Vec<(TestResult, Vec<KeyPress>)>>>;
+
+struct Harness {
+    text: OpenText,
+    clock: ManualClock,
+    saved: Saved,
+}
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+fn open(text: &str, test_log: Vec<TestResult>) -> Harness {
+    let clock = ManualClock::new(start());
+    let saved = Saved::default();
+    let sink = saved.clone();
+    let text = OpenText::with_clock(
+        text.to_string(),
+        test_log,
+        move |record: &Record| {
+            let mut saved = sink.borrow_mut();
+            match record {
+                Record::Test(test) => saved.push((test.clone(), Vec::new())),
+                Record::KeyPresses(keypresses) => {
+                    saved.last_mut().unwrap().1.extend_from_slice(keypresses)
+                }
+            }
+            Ok(())
+        },
+        clock.clone(),
+    );
+    Harness { text, clock, saved }
+}
+
+/// A book made of `n` repetitions of "word ".
+fn words(n: usize) -> String {
+    "word ".repeat(n)
+}
+
+impl Harness {
+    /// Types `keys`, one every 100ms.
+    fn type_keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            self.clock.advance(Duration::milliseconds(100));
+            self.text.handle_char(c);
+        }
+    }
+
+    /// Types the rest of the current test correctly.
+    fn finish_test(&mut self) {
+        let test = self.text.test.unwrap();
+        let remaining = (test.start_index + test.cur_char..test.start_index + test.length)
+            .map(|i| self.text.text[i].to_string())
+            .collect::<String>();
+        self.type_keys(&remaining);
+    }
+
+    fn test_bounds(&self) -> (usize, usize) {
+        let test = self.text.test.unwrap();
+        (test.start_index, test.length)
+    }
+}
+
+#[test]
+fn first_test_is_starting_size_extended_to_the_end_of_a_word() {
+    let h = open(&words(200), Vec::new());
+    // 105 glyphs, then the rest of the word which follows and its space.
+    assert_eq!(h.test_bounds(), (0, 110));
+    assert_eq!(h.text.focused_glyph, 0);
+}
+
+#[test]
+fn test_is_limited_to_the_end_of_the_book() {
+    let h = open(&words(10), Vec::new());
+    assert_eq!(h.test_bounds(), (0, 50));
+}
+
+#[test]
+fn success_moves_to_a_longer_test_after_it() {
+    let mut h = open(&words(200), Vec::new());
+    h.finish_test();
+
+    let saved = h.saved.borrow();
+    assert_eq!(saved.len(), 1);
+    let result = &saved[0].0;
+    assert!(result.succeeded);
+    assert_eq!((result.start_index, result.end_index), (0, 110));
+    drop(saved);
+
+    // The longest recent test was 110 glyphs, so 115 glyphs plus a word.
+    assert_eq!(h.test_bounds(), (110, 120));
+    assert_eq!(h.text.focused_glyph, 110);
+}
+
+#[test]
+fn failure_retries_the_same_passage() {
+    let mut h = open(&words(200), Vec::new());
+    h.type_keys("wor");
+    assert_eq!(h.text.test.unwrap().cur_char, 3);
+    h.type_keys("x");
+
+    let saved = h.saved.borrow();
+    let result = &saved[0].0;
+    assert!(!result.succeeded);
+    assert_eq!((result.start_index, result.end_index), (0, 3));
+    drop(saved);
+
+    // Failures of five glyphs or fewer don't affect the size of the next test.
+    assert_eq!(h.test_bounds(), (0, 110));
+}
+
+#[test]
+fn failures_shorten_the_next_test() {
+    let mut h = open(&words(200), Vec::new());
+    h.type_keys("word word x");
+
+    // The longest recent test was the 10 glyph failure, which is shortened by
+    // five glyphs for the one failure since the last success.
+    assert_eq!(h.test_bounds(), (0, 15));
+}
+
+#[test]
+fn sizing_follows_previous_sessions() {
+    let t = start();
+    let log = vec![
+        TestResult {
+            succeeded: true,
+            start_index: 0,
+            end_index: 200,
+            started: t,
+            completed: t,
+            pauses: Vec::new(),
+        },
+        TestResult {
+            succeeded: false,
+            start_index: 200,
+            end_index: 240,
+            started: t,
+            completed: t,
+            pauses: Vec::new(),
+        },
+    ];
+    let h = open(&words(200), log);
+    // avg_50 is 4 and max_10 is 200, so 205 glyphs less 5 for the failure,
+    // then the next word.
+    assert_eq!(h.test_bounds(), (200, 205));
+}
+
+#[test]
+fn book_is_finished_when_every_test_has_succeeded() {
+    let mut h = open(&words(10), Vec::new());
+    h.finish_test();
+    assert!(h.text.test.is_none());
+    assert_eq!(h.text.focused_glyph, 49);
+}
+
+#[test]
+fn keypresses_are_logged_with_the_clock_time() {
+    let mut h = open(&words(200), Vec::new());
+    h.type_keys("wo");
+    h.clock.advance(Duration::seconds(5));
+    h.type_keys("x");
+
+    let saved = h.saved.borrow();
+    let (result, keypresses) = &saved[0];
+    let logged = keypresses
+        .iter()
+        .map(|k| (k.key.as_str(), k.correct, k.time))
+        .collect::<Vec<_>>();
+    let ms = Duration::milliseconds;
+    assert_eq!(
+        logged,
+        vec![
+            ("w", true, start() + ms(100)),
+            ("o", true, start() + ms(200)),
+            ("x", false, start() + ms(5300)),
+        ]
+    );
+    assert_eq!(result.started, start() + ms(100));
+    assert_eq!(result.completed, start() + ms(5300));
+}
+
+#[test]
+fn keypresses_are_saved_with_the_test_they_belong_to() {
+    let mut h = open(&words(200), Vec::new());
+    h.type_keys("x");
+    h.finish_test();
+
+    let saved = h.saved.borrow();
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].1.len(), 1);
+    assert_eq!(saved[1].1.len(), 110);
+    assert!(saved[1].1.iter().all(|k| k.correct));
+}
+
+#[test]
+fn started_is_reset_by_the_first_correct_keypress() {
+    let mut h = open(&words(200), Vec::new());
+    h.clock.advance(Duration::minutes(10));
+    h.finish_test();
+
+    let saved = h.saved.borrow();
+    let result = &saved[0].0;
+    assert_eq!(
+        result.started,
+        start() + Duration::minutes(10) + Duration::milliseconds(100)
+    );
+    // 109 more keypresses, 100ms apart.
+    assert_eq!(result.duration_secs(), 10.9);
+}
+
+#[test]
+fn idle_time_is_not_counted() {
+    let mut h = open(&words(200), Vec::new());
+    h.type_keys("wo");
+    h.text.pause_if_idle(Duration::seconds(10));
+    assert!(!h.text.is_paused());
+
+    h.clock.advance(Duration::minutes(5));
+    h.text.pause_if_idle(Duration::seconds(10));
+    assert!(h.text.is_paused());
+    h.finish_test();
+    assert!(!h.text.is_paused());
+
+    let saved = h.saved.borrow();
+    let result = &saved[0].0;
+    let pause = result.pauses[0];
+    assert_eq!(result.pauses.len(), 1);
+    assert_eq!(
+        pause.start,
+        start() + Duration::milliseconds(200) + Duration::seconds(10)
+    );
+    assert_eq!(
+        pause.end,
+        start() + Duration::minutes(5) + Duration::milliseconds(300)
+    );
+    // 100ms between each of the 110 keypresses, except for the 10 seconds
+    // before the pause.
+    assert_eq!(result.duration_secs(), 10.8 + 10.0);
+}
+
+#[test]
+fn tests_are_not_paused_before_they_start() {
+    let mut h = open(&words(200), Vec::new());
+    h.clock.advance(Duration::minutes(5));
+    h.text.pause_if_idle(Duration::seconds(10));
+    assert!(!h.text.is_paused());
+}
+
+#[test]
+fn results_without_pauses_are_upgraded() {
+    let old = r#"{"succeeded":true,"start_index":0,"end_index":5,"started":0,"completed":1000000,"version":1}"#;
+    let (result, upgraded) =
+        schema::decode::<TestResult>(serde_json::from_str(old).unwrap()).unwrap();
+    assert!(upgraded);
+    assert!(result.pauses.is_empty());
+    assert_eq!(result.duration_secs(), 1.0);
+}
+
+/// Opens a book whose saves write to `saved`, except for keypresses while
+/// `failing` is set.
+fn open_failing_keypresses(
+    saved: Rc<RefCell<Vec<Record>>>,
+    failing: Rc<RefCell<bool>>,
+) -> OpenText {
+    OpenText::with_clock(
+        words(10),
+        Vec::new(),
+        move |record: &Record| {
+            if *failing.borrow() && matches!(record, Record::KeyPresses(_)) {
+                return Err("disk full".into());
+            }
+            saved.borrow_mut().push(record.clone());
+            Ok(())
+        },
+        ManualClock::new(start()),
+    )
+}
+
+#[test]
+fn failed_saves_are_kept_and_retried() {
+    let saved = Rc::new(RefCell::new(Vec::new()));
+    let failing = Rc::new(RefCell::new(true));
+    let mut text = open_failing_keypresses(saved.clone(), failing.clone());
+    text.handle_char('x');
+    assert!(text.save_error().is_some());
+    // The test was saved, so only its keypresses are left.
+    assert!(matches!(saved.borrow()[..], [Record::Test(_)]));
+    assert!(matches!(text.unsaved(), [Record::KeyPresses(_)]));
+    assert!(!text.retry_saves());
+    assert_eq!(saved.borrow().len(), 1);
+
+    *failing.borrow_mut() = false;
+    assert!(text.retry_saves());
+    assert!(text.unsaved().is_empty());
+    assert!(text.save_error().is_none());
+    assert!(matches!(
+        saved.borrow()[..],
+        [Record::Test(_), Record::KeyPresses(_)]
+    ));
+}
+
+#[test]
+fn later_records_wait_for_earlier_ones() {
+    let saved = Rc::new(RefCell::new(Vec::new()));
+    let failing = Rc::new(RefCell::new(true));
+    let mut text = open_failing_keypresses(saved.clone(), failing.clone());
+    text.handle_char('x');
+    text.handle_char('x');
+    assert_eq!(saved.borrow().len(), 1);
+    assert_eq!(text.unsaved().len(), 3);
+
+    *failing.borrow_mut() = false;
+    assert!(text.retry_saves());
+    let saved = saved.borrow();
+    assert!(matches!(
+        saved[..],
+        [
+            Record::Test(_),
+            Record::KeyPresses(_),
+            Record::Test(_),
+            Record::KeyPresses(_)
+        ]
+    ));
+}
+
+#[test]
+fn failed_saves_are_retried_on_tick() {
+    let saved = Rc::new(RefCell::new(Vec::new()));
+    let failing = Rc::new(RefCell::new(true));
+    let mut text = open_failing_keypresses(saved.clone(), failing.clone());
+    let mut app = App::new(());
+    text.handle_char('x');
+    app.tick(&mut text);
+    assert_eq!(text.unsaved().len(), 1);
+
+    *failing.borrow_mut() = false;
+    app.tick(&mut text);
+    assert!(text.unsaved().is_empty());
+    assert!(text.save_error().is_none());
+}
+
+#[test]
+fn records_which_fail_in_the_background_are_saved_again() {
+    let saved = Rc::new(RefCell::new(Vec::new()));
+    let failing = Rc::new(RefCell::new(false));
+    let mut text = open_failing_keypresses(saved.clone(), failing);
+    let mut app = App::new(());
+    text.handle_char('x');
+    let keypresses = saved.borrow()[1].clone();
+    text.requeue(keypresses.clone(), "quota exceeded");
+    assert!(text.save_error().unwrap().contains("quota exceeded"));
+    assert_eq!(text.unsaved(), std::slice::from_ref(&keypresses));
+
+    app.tick(&mut text);
+    assert!(text.unsaved().is_empty());
+    assert!(text.save_error().is_none());
+    assert_eq!(saved.borrow().last(), Some(&keypresses));
+}
+
+#[test]
+fn quitting_with_unsaved_records_warns_first() {
+    let saved = Rc::new(RefCell::new(Vec::new()));
+    let failing = Rc::new(RefCell::new(true));
+    let mut text = open_failing_keypresses(saved, failing.clone());
+    let mut app = App::new(());
+    text.handle_char('x');
+    app.quit(&mut text);
+    assert!(app.running);
+    assert!(app.error.as_deref().unwrap().contains("again to quit"));
+    app.quit(&mut text);
+    assert!(!app.running);
+
+    // Nothing to warn about once the retry succeeds.
+    let mut app = App::new(());
+    *failing.borrow_mut() = false;
+    app.quit(&mut text);
+    assert!(!app.running);
+}
+
+#[test]
+fn committed_input_is_typed_glyph_by_glyph() {
+    let mut h = open(&words(200), Vec::new());
+    h.text.handle_input("word wo");
+    assert_eq!(h.text.test.unwrap().cur_char, 7);
+    assert!(h.saved.borrow().is_empty());
+}
+
+#[test]
+fn committed_input_after_a_mistake_is_dropped() {
+    let mut h = open(&words(200), Vec::new());
+    h.text.handle_input("worx word");
+
+    let saved = h.saved.borrow();
+    assert_eq!(saved.len(), 1);
+    assert_eq!((saved[0].0.start_index, saved[0].0.end_index), (0, 3));
+    assert_eq!(saved[0].1.len(), 4);
+    assert_eq!(h.text.test.unwrap().cur_char, 0);
+}
+
+#[test]
+fn composed_glyphs_match_decomposed_text() {
+    // "café" with a combining acute accent, as a dead key wouldn't type it.
+    let mut h = open(&"cafe\u{301} ".repeat(50), Vec::new());
+    h.text.handle_input("caf\u{e9}");
+    assert_eq!(h.text.test.unwrap().cur_char, 4);
+    assert!(h.saved.borrow().is_empty());
+}
+
+#[test]
+fn moving_starts_the_next_test_at_the_new_position() {
+    let mut h = open(&words(200), Vec::new());
+    h.type_keys("wor");
+    h.text.move_to(500);
+
+    assert_eq!(h.test_bounds().0, 500);
+    assert_eq!(h.text.focused_glyph, 500);
+    let saved = h.saved.borrow();
+    assert_eq!(saved.len(), 1);
+    assert_eq!((saved[0].0.start_index, saved[0].0.end_index), (500, 500));
+    assert!(saved[0].1.is_empty());
+}
+
+#[test]
+fn moves_which_arent_saved_are_forgotten_on_reopening() {
+    let mut h = open(&words(200), Vec::new());
+    h.text.move_without_saving(500);
+    assert_eq!(h.test_bounds().0, 500);
+    assert!(h.saved.borrow().is_empty());
+
+    let reopened = open(&words(200), Vec::new());
+    assert_eq!(reopened.test_bounds().0, 0);
+}
+
+#[test]
+fn chapters_are_found_once() {
+    let h = open("Chapter 1\n\nOne.\n\nChapter 2\n\nTwo.", Vec::new());
+    assert_eq!(h.text.chapters(), [(1, 0), (2, 17)]);
+    assert!(std::ptr::eq(h.text.chapters(), h.text.chapters()));
+}
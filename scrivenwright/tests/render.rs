@@ -0,0 +1,304 @@
+//! Renders `App` into a `TestBackend` after scripted keypresses, and compares
+//! the buffer, including its styles, with a snapshot in `tests/snapshots`.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to accept changes to the rendering, or to
+//! write a new snapshot. A missing snapshot fails the test.
+
+use chrono::{TimeZone, Utc};
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::Terminal;
+use scrivenwright::app::{App, PlatformAdapter, TextHandle};
+use scrivenwright::handler::{KeyCode as K, KeyDown, KeyModifiers as M, MouseEvent};
+use scrivenwright::text::{OpenText, TestResult};
+use scrivenwright::theme::{Theme, BUILTIN_THEMES};
+use std::{env, fs, path::PathBuf};
+
+const SIZES: &[(u16, u16)] = &[(80, 24), (40, 10), (120, 40)];
+
+const BOOK: &str = "It was the best of times, it was the worst of times, it was the age of \
+wisdom, it was the age of foolishness, it was the epoch of belief, it was the epoch of \
+incredulity, it was the season of Light, it was the season of Darkness, it was the spring of \
+hope, it was the winter of despair.\n\nWe had everything before us, we had nothing before us, \
+we were all going direct to Heaven, we were all going direct the other way.";
+
+fn key(code: K) -> KeyDown {
+    KeyDown {
+        code,
+        mods: M::Unimplemented,
+    }
+}
+
+fn keys(s: &str) -> Vec<KeyDown> {
+    s.chars().map(|c| key(K::Char(c))).collect()
+}
+
+fn open(book: &str, test_log: Vec<TestResult>) -> OpenText {
+    OpenText::new(book.to_string(), test_log, |_| Ok(()))
+}
+
+/// Plays `script` into a fresh `App`, then renders it.
+fn render(text: &mut OpenText, script: &[KeyDown], width: u16, height: u16) -> Buffer {
+    render_themed(text, script, width, height, Theme::default())
+}
+
+fn render_themed(
+    text: &mut OpenText,
+    script: &[KeyDown],
+    width: u16,
+    height: u16,
+    theme: Theme,
+) -> Buffer {
+    let mut app = App::new(());
+    app.settings.theme = theme;
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    for &key_down in script {
+        app.handle_key_events(key_down, text, width.saturating_sub(2));
+    }
+    terminal.draw(|frame| app.render(text, frame)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn assert_snapshot(name: &str, buffer: &Buffer) {
+    let actual = format!("{:#?}\n", buffer);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.snap", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!(
+            "{} is missing. The rendering was:\n{}\nRun with UPDATE_SNAPSHOTS=1 to write it.",
+            path.display(),
+            actual
+        );
+    };
+    assert!(
+        expected == actual,
+        "{} doesn't match the rendering:\n{}\nRun with UPDATE_SNAPSHOTS=1 if the change is intended.",
+        path.display(),
+        actual
+    );
+}
+
+/// Snapshots a script at each of the usual sizes.
+fn snapshot_sizes(name: &str, book: &str, test_log: Vec<TestResult>, script: &[KeyDown]) {
+    for &(width, height) in SIZES {
+        let mut text = open(book, test_log.clone());
+        let rendered = render(&mut text, script, width, height);
+        assert_snapshot(&format!("{}_{}x{}", name, width, height), &rendered);
+    }
+}
+
+#[test]
+fn start_of_book() {
+    snapshot_sizes("start", BOOK, Vec::new(), &[]);
+}
+
+#[test]
+fn partway_through_a_test() {
+    snapshot_sizes("partway", BOOK, Vec::new(), &keys("It was the best"));
+}
+
+#[test]
+fn after_a_failure() {
+    snapshot_sizes("failure", BOOK, Vec::new(), &keys("It was x"));
+}
+
+#[test]
+fn cursor_moved_away_and_back() {
+    let mut script = keys("It was");
+    script.extend([key(K::Down), key(K::Down), key(K::Right)]);
+    snapshot_sizes("moved", BOOK, Vec::new(), &script);
+    script.push(key(K::Esc));
+    snapshot_sizes("snapped_back", BOOK, Vec::new(), &script);
+}
+
+#[test]
+fn full_width() {
+    let script = [KeyDown {
+        code: K::Char('f'),
+        mods: M::Ctrl,
+    }];
+    snapshot_sizes("full_width", BOOK, Vec::new(), &script);
+}
+
+#[test]
+fn later_in_the_book() {
+    let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let log = vec![TestResult::bookmark(300, t)];
+    snapshot_sizes("later", BOOK, log, &[]);
+}
+
+#[test]
+fn finished_book() {
+    let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let log = vec![TestResult::bookmark(BOOK.chars().count(), t)];
+    snapshot_sizes("finished", BOOK, log, &[]);
+}
+
+#[test]
+fn empty_book() {
+    snapshot_sizes("empty", "", Vec::new(), &keys("abc"));
+}
+
+#[test]
+fn error_banner() {
+    let mut text = open(BOOK, Vec::new());
+    let mut app = App::new(());
+    app.error = Some("Couldn't save settings".to_string());
+    let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+    terminal.draw(|frame| app.render(&text, frame)).unwrap();
+    assert_snapshot("error_banner", terminal.backend().buffer());
+
+    app.handle_key_events(key(K::Esc), &mut text, 38);
+    assert!(app.error.is_none());
+}
+
+#[test]
+fn tiny_terminals() {
+    let mut script = keys("It wa");
+    script.extend([key(K::Up), key(K::Down), key(K::Left), key(K::Right)]);
+    for width in 0..6 {
+        for height in 0..6 {
+            for book in [BOOK, ""] {
+                let mut text = open(book, Vec::new());
+                render(&mut text, &script, width, height);
+            }
+        }
+    }
+    let mut text = open(BOOK, Vec::new());
+    assert_snapshot("tiny_3x3", &render(&mut text, &script, 3, 3));
+}
+
+#[test]
+fn themes() {
+    for name in BUILTIN_THEMES {
+        let mut text = open(BOOK, Vec::new());
+        let mut script = keys("It was");
+        script.push(key(K::Down));
+        let theme = Theme::builtin(name).unwrap();
+        let rendered = render_themed(&mut text, &script, 40, 10, theme);
+        assert_snapshot(&format!("theme_{}", name), &rendered);
+    }
+}
+
+struct Shelf;
+
+impl PlatformAdapter for Shelf {
+    fn get_texts(&self) -> Vec<TextHandle> {
+        let handle = |name: &str, author: Option<&str>, position, chapters| TextHandle {
+            name: name.to_string(),
+            author: author.map(str::to_string),
+            length: 400,
+            position,
+            chapters,
+        };
+        vec![
+            handle(
+                "A Tale of Two Cities",
+                Some("Charles Dickens"),
+                Some(100),
+                45,
+            ),
+            handle("notes", None, Some(400), 0),
+            handle("The Raven", Some("Edgar Allan Poe"), None, 0),
+        ]
+    }
+}
+
+#[test]
+fn library() {
+    let mut text = open(BOOK, Vec::new());
+    let mut app = App::new(Shelf);
+    app.book_title = "notes".to_string();
+    let ctrl_l = KeyDown {
+        code: K::Char('l'),
+        mods: M::Ctrl,
+    };
+    let mut terminal = Terminal::new(TestBackend::new(80, 8)).unwrap();
+    for key_down in [ctrl_l, key(K::Char('x')), key(K::Down)] {
+        app.handle_key_events(key_down, &mut text, 78);
+    }
+    terminal.draw(|frame| app.render(&text, frame)).unwrap();
+    assert_snapshot("library", terminal.backend().buffer());
+    assert_eq!(text.test.unwrap().cur_char, 0);
+
+    app.handle_key_events(key(K::Char('\n')), &mut text, 78);
+    assert!(app.library.is_none());
+    assert_eq!(app.chosen.as_deref(), Some("The Raven"));
+}
+
+#[test]
+fn clicking_a_glyph() {
+    let mut text = open(BOOK, Vec::new());
+    let mut app = App::new(());
+    let screen = Rect::new(0, 0, 80, 24);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+
+    // Outside the text.
+    app.handle_mouse_events(MouseEvent::Click { column: 5, row: 10 }, &mut text, screen);
+    assert!(app.inspected.is_none());
+
+    // The "w" of "worst".
+    app.handle_mouse_events(
+        MouseEvent::Click {
+            column: 53,
+            row: 10,
+        },
+        &mut text,
+        screen,
+    );
+    assert_eq!(app.inspected.as_ref().map(|i| i.index), Some(37));
+    assert_eq!(text.focused_glyph, 37);
+    terminal.draw(|frame| app.render(&text, frame)).unwrap();
+    assert_snapshot("inspect", terminal.backend().buffer());
+
+    app.handle_key_events(key(K::Char('\n')), &mut text, 78);
+    assert!(app.inspected.is_none());
+    assert_eq!(text.test.unwrap().start_index, 37);
+}
+
+#[test]
+fn scrolling() {
+    let mut text = open(BOOK, Vec::new());
+    let mut app = App::new(());
+    let screen = Rect::new(0, 0, 80, 24);
+    app.handle_mouse_events(MouseEvent::ScrollDown, &mut text, screen);
+    assert_eq!(text.focused_glyph, 43);
+    app.handle_mouse_events(MouseEvent::ScrollUp, &mut text, screen);
+    assert_eq!(text.focused_glyph, 0);
+}
+
+#[test]
+fn clicking_in_the_library() {
+    let mut text = open(BOOK, Vec::new());
+    let mut app = App::new(Shelf);
+    app.open_library();
+    let screen = Rect::new(0, 0, 80, 8);
+    let third = MouseEvent::Click { column: 10, row: 3 };
+    app.handle_mouse_events(third, &mut text, screen);
+    assert_eq!(app.library.as_ref().unwrap().selected, 2);
+
+    app.handle_mouse_events(third, &mut text, screen);
+    assert!(app.library.is_none());
+    assert_eq!(app.chosen.as_deref(), Some("The Raven"));
+}
+
+#[test]
+fn paused() {
+    let mut text = open(BOOK, Vec::new());
+    let mut app = App::new(());
+    app.handle_text("It was", &mut text);
+    text.pause_if_idle(chrono::Duration::zero());
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|frame| app.render(&text, frame)).unwrap();
+    assert_snapshot("paused", terminal.backend().buffer());
+
+    app.handle_text(" ", &mut text);
+    assert!(!text.is_paused());
+}
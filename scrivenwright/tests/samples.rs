@@ -0,0 +1,39 @@
+#![cfg(feature = "samples")]
+
+use scrivenwright::samples::{self, SAMPLES};
+
+#[test]
+fn samples_decompress_to_their_text() {
+    let sample = samples::find("The Raven").unwrap();
+    assert_eq!(sample.text(), include_str!("../samples/the-raven.txt"));
+    for sample in SAMPLES {
+        assert!(!sample.text().is_empty(), "{} is empty", sample.title);
+    }
+}
+
+#[test]
+fn samples_are_normalized() {
+    for sample in SAMPLES {
+        let text = sample.text();
+        assert!(
+            !text.contains('\r') && !text.ends_with('\n'),
+            "{}",
+            sample.title
+        );
+        assert!(!text.contains("  "), "{}", sample.title);
+        let lone_newline = text.split("\n\n").any(|paragraph| paragraph.contains('\n'));
+        assert!(!lone_newline, "{}", sample.title);
+    }
+}
+
+#[test]
+fn excerpts_of_novels_are_chapter_indexed() {
+    let chapters = samples::find("Pride and Prejudice (Chapters 1-2)")
+        .unwrap()
+        .chapters();
+    assert_eq!(
+        chapters.iter().map(|&(n, _)| n).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(chapters[0].1, 0);
+}
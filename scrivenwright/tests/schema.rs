@@ -0,0 +1,140 @@
+use scrivenwright::bundle::Bundle;
+use scrivenwright::schema::{self, DecodeError, DecodedLog, Versioned};
+use scrivenwright::text::{KeyPress, TestResult};
+use serde_json::{json, Value};
+
+fn keypress(key: &str, time: i64) -> Value {
+    json!({"correct": true, "key": key, "time": time, "version": 1})
+}
+
+fn keys(log: &DecodedLog<KeyPress>) -> Vec<&str> {
+    log.records.iter().map(|k| k.key.as_str()).collect()
+}
+
+/// Writes a log back out the way the terminal app rewrites outdated logs.
+fn rewrite(log: &DecodedLog<KeyPress>) -> String {
+    let mut contents = String::new();
+    for record in &log.records {
+        contents.push_str(&schema::encode(record).unwrap());
+        contents.push('\n');
+    }
+    for error in &log.errors {
+        contents.push_str(&error.content);
+        contents.push('\n');
+    }
+    contents
+}
+
+fn has_all_migrations<T: Versioned>() -> bool {
+    T::MIGRATIONS.len() == T::VERSION as usize
+}
+
+#[test]
+fn every_version_has_a_migration() {
+    assert!(has_all_migrations::<KeyPress>());
+    assert!(has_all_migrations::<TestResult>());
+    assert!(has_all_migrations::<Bundle>());
+}
+
+#[test]
+fn records_are_written_with_their_version() {
+    let (record, upgraded) = schema::decode::<KeyPress>(keypress("a", 0)).unwrap();
+    assert!(!upgraded);
+    let encoded: Value = serde_json::from_str(&schema::encode(&record).unwrap()).unwrap();
+    assert_eq!(encoded, keypress("a", 0));
+}
+
+#[test]
+fn unversioned_records_are_upgraded() {
+    let old = json!({"succeeded": false, "start_index": 3, "end_index": 7,
+        "started": 0, "completed": 2_000_000});
+    let (test, upgraded) = schema::decode::<TestResult>(old).unwrap();
+    assert!(upgraded);
+    assert_eq!((test.start_index, test.end_index), (3, 7));
+    assert!(test.pauses.is_empty());
+}
+
+#[test]
+fn bad_versions_are_reported() {
+    let newer = json!({"correct": true, "key": "a", "time": 0, "version": 99});
+    assert!(matches!(
+        schema::decode::<KeyPress>(newer),
+        Err(DecodeError::UnknownVersion(99))
+    ));
+    let text = json!({"correct": true, "key": "a", "time": 0, "version": "one"});
+    assert!(matches!(
+        schema::decode::<KeyPress>(text),
+        Err(DecodeError::InvalidVersion(_))
+    ));
+    assert!(matches!(
+        schema::decode::<KeyPress>(json!([1])),
+        Err(DecodeError::NotAnObject)
+    ));
+}
+
+#[test]
+fn unreadable_lines_are_kept() {
+    let contents = format!("{}\nnot json\n\n{}\n", keypress("a", 0), keypress("b", 1));
+    let log = schema::decode_log::<KeyPress>(&contents);
+    assert_eq!(keys(&log), ["a", "b"]);
+    assert!(!log.outdated);
+    assert_eq!(log.errors.len(), 1);
+    assert_eq!(log.errors[0].line, 2);
+    assert_eq!(log.errors[0].content, "not json");
+}
+
+#[test]
+fn arrays_are_outdated() {
+    let contents = json!([keypress("a", 0), keypress("b", 1)]).to_string();
+    let log = schema::decode_log::<KeyPress>(&contents);
+    assert_eq!(keys(&log), ["a", "b"]);
+    assert!(log.outdated);
+    assert!(log.errors.is_empty());
+}
+
+#[test]
+fn records_in_arrays_are_reported_by_line() {
+    let newer = json!({"correct": true, "key": "x", "time": 0, "version": 99});
+    let contents = format!("[\n{},\n\n{}\n]", keypress("a", 0), newer);
+    let log = schema::decode_log::<KeyPress>(&contents);
+    assert_eq!(keys(&log), ["a"]);
+    assert_eq!(log.errors.len(), 1);
+    assert_eq!(log.errors[0].line, 4);
+}
+
+#[test]
+fn arrays_written_over_longer_arrays_are_salvaged() {
+    // A short array written over a longer one, followed by records appended
+    // after the logs became newline delimited.
+    let contents = format!(
+        "{}{{\"key\":\"x\",\"time\":5}}]{}\n{}\n",
+        json!([keypress("a", 0), keypress("b", 1)]),
+        keypress("c", 2),
+        keypress("d", 3)
+    );
+    let log = schema::decode_log::<KeyPress>(&contents);
+    assert_eq!(keys(&log), ["a", "b", "c", "d"]);
+    assert!(log.outdated);
+    assert_eq!(log.errors.len(), 1);
+    assert_eq!(log.errors[0].content, r#"{"key":"x","time":5}]"#);
+
+    // Once rewritten, the damage is on its own line and is left alone.
+    let rewritten = schema::decode_log::<KeyPress>(&rewrite(&log));
+    assert_eq!(keys(&rewritten), ["a", "b", "c", "d"]);
+    assert!(!rewritten.outdated);
+    assert_eq!(rewritten.errors.len(), 1);
+    assert_eq!(rewritten.errors[0].content, log.errors[0].content);
+}
+
+#[test]
+fn truncated_arrays_keep_their_first_records() {
+    let contents = format!("[{},{},{{\"corr", keypress("a", 0), keypress("b", 1));
+    let log = schema::decode_log::<KeyPress>(&contents);
+    assert_eq!(keys(&log), ["a", "b"]);
+    assert_eq!(log.errors.len(), 1);
+    assert_eq!(log.errors[0].content, "{\"corr");
+
+    let damaged = schema::decode_log::<KeyPress>("[not json");
+    assert!(damaged.records.is_empty());
+    assert_eq!(damaged.errors[0].content, "[not json");
+}
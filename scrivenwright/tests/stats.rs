@@ -0,0 +1,67 @@
+use chrono::{TimeZone, Utc};
+use proptest::prelude::*;
+use scrivenwright::stats::RollingStats;
+use scrivenwright::text::TestResult;
+
+fn tests() -> impl Strategy<Value = Vec<TestResult>> {
+    let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    prop::collection::vec((any::<bool>(), 0usize..1000, 0usize..200), 0..200).prop_map(
+        move |tests| {
+            tests
+                .into_iter()
+                .map(|(succeeded, start_index, len)| TestResult {
+                    succeeded,
+                    start_index,
+                    end_index: start_index + len,
+                    started: t,
+                    completed: t,
+                    pauses: Vec::new(),
+                })
+                .collect()
+        },
+    )
+}
+
+fn counted(tests: &[TestResult]) -> impl Iterator<Item = usize> + '_ {
+    tests.iter().map(|t| t.len()).filter(|&len| len > 5).rev()
+}
+
+proptest! {
+    /// The accumulator agrees with rescanning the whole log.
+    #[test]
+    fn matches_rescanning(tests in tests()) {
+        let stats = RollingStats::new(&tests);
+        prop_assert_eq!(stats.average_len(), counted(&tests).take(50).sum::<usize>() / 50);
+        prop_assert_eq!(stats.recent_average_len(), counted(&tests).take(10).sum::<usize>() / 10);
+        prop_assert_eq!(stats.recent_max_len(), counted(&tests).take(10).max());
+        prop_assert_eq!(
+            stats.failure_streak(),
+            tests
+                .iter()
+                .rev()
+                .take_while(|t| !t.succeeded || t.is_empty())
+                .filter(|t| !t.succeeded && t.len() > 5)
+                .count()
+        );
+        prop_assert_eq!(
+            stats.position(),
+            tests.iter().rfind(|t| t.succeeded).map_or(0, |t| t.end_index)
+        );
+    }
+}
+
+#[test]
+fn bookmarks_move_the_position_but_keep_the_failure_streak() {
+    let t = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let failure = TestResult {
+        succeeded: false,
+        start_index: 0,
+        end_index: 20,
+        started: t,
+        completed: t,
+        pauses: Vec::new(),
+    };
+    let stats = RollingStats::new(&[failure.clone(), failure, TestResult::bookmark(300, t)]);
+    assert_eq!(stats.failure_streak(), 2);
+    assert_eq!(stats.position(), 300);
+}
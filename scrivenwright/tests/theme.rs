@@ -0,0 +1,61 @@
+use ratatui::style::Color;
+use scrivenwright::settings::Settings;
+use scrivenwright::theme::{Highlight, Theme, BUILTIN_THEMES};
+
+#[test]
+fn builtin_themes_round_trip_through_toml() {
+    for name in BUILTIN_THEMES {
+        let theme = Theme::builtin(name).unwrap();
+        let toml = theme.to_toml().unwrap();
+        assert_eq!(Theme::from_toml(&toml).unwrap(), theme, "{}", toml);
+        assert_eq!(theme.builtin_name(), Some(*name));
+    }
+}
+
+#[test]
+fn partial_theme_is_based_on_dark() {
+    let theme = Theme::from_toml(
+        r##"
+        untyped = "#0A0B0C"
+        [current]
+        bg = "light magenta"
+        "##,
+    )
+    .unwrap();
+    assert_eq!(theme.untyped, Color::Rgb(10, 11, 12));
+    assert_eq!(
+        theme.current,
+        Highlight::new(Color::Black, Color::LightMagenta)
+    );
+    assert_eq!(theme.typed, Theme::dark().typed);
+    assert_eq!(theme.builtin_name(), None);
+}
+
+#[test]
+fn theme_can_be_based_on_a_builtin() {
+    let theme = Theme::from_toml(
+        r#"
+        base = "solarized"
+        border = "208"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(theme.border, Color::Indexed(208));
+    assert_eq!(theme.background, Theme::solarized().background);
+}
+
+#[test]
+fn bad_themes_are_rejected() {
+    assert!(Theme::from_toml(r#"typed = "chartreuse-ish""#).is_err());
+    assert!(Theme::from_toml(r#"base = "nord""#).is_err());
+    assert!(Theme::from_toml("typed = ").is_err());
+}
+
+#[test]
+fn settings_without_a_theme_use_dark() {
+    let settings: Settings = serde_json::from_str(r#"{"text_width_percent": 60}"#).unwrap();
+    assert_eq!(settings.theme, Theme::dark());
+    let json = serde_json::to_string(&Settings::default()).unwrap();
+    let settings: Settings = serde_json::from_str(&json).unwrap();
+    assert_eq!(settings.theme, Theme::dark());
+}
@@ -0,0 +1,96 @@
+//! Invariants of `GlyphString` and `TextWrapper` over arbitrary text. The
+//! fuzz target in `fuzz/` checks the same properties.
+
+use proptest::prelude::*;
+use scrivenwright::glyph_string::GlyphString;
+use scrivenwright::text_wrapper::{Dir, TextWrapper};
+
+/// Text which looks like prose, with the occasional awkward character, as
+/// well as completely arbitrary text.
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z]{1,12}([ \n-]{1,3}[a-z]{1,12}){0,40}",
+        "([a-zé ]|\n|\r\n|  |\t|e\u{301}|🇳🇿|👩‍👩‍👧|字|\u{200b}|-){0,200}",
+        any::<String>(),
+    ]
+}
+
+fn all_lines(wrapper: &TextWrapper, text: &GlyphString, width: u16) -> Vec<(usize, usize)> {
+    wrapper.lines(text, width, 0, usize::MAX)
+}
+
+proptest! {
+    #[test]
+    fn glyphs_tile_the_string(s in text()) {
+        let text = GlyphString::new(s.clone());
+        let mut end = 0;
+        for (i, &(start, glyph_end)) in text.byte_ranges().iter().enumerate() {
+            prop_assert_eq!(start, end);
+            prop_assert!(glyph_end > start);
+            prop_assert_eq!(text.index_of_ptr(start), i);
+            end = glyph_end;
+        }
+        prop_assert_eq!(end, s.len());
+        prop_assert_eq!(text.glyphs().collect::<String>(), s);
+    }
+
+    #[test]
+    fn lines_tile_the_glyphs(s in text(), width in 0u16..120) {
+        let text = GlyphString::new(s);
+        let lines = all_lines(&TextWrapper::new(), &text, width);
+        let mut end = 0;
+        for &(start, line_end) in &lines {
+            prop_assert_eq!(start, end);
+            prop_assert!(line_end > start);
+            end = line_end;
+        }
+        prop_assert_eq!(end, text.len());
+    }
+
+    #[test]
+    fn every_glyph_is_on_its_line(s in text(), width in 0u16..120) {
+        let text = GlyphString::new(s);
+        let wrapper = TextWrapper::new();
+        let lines = all_lines(&wrapper, &text, width);
+        for idx in 0..text.len() {
+            let (line, offset) = wrapper.line_offset_of_idx(&text, width, idx).unwrap();
+            let (start, end) = lines[line];
+            prop_assert_eq!(start + offset, idx);
+            prop_assert!(idx < end);
+        }
+        prop_assert_eq!(wrapper.line_offset_of_idx(&text, width, text.len()), None);
+    }
+
+    #[test]
+    fn cursor_moves_stay_in_bounds(s in text(), width in 0u16..120, moves in prop::collection::vec(0..4u8, 0..50)) {
+        let text = GlyphString::new(s);
+        let mut wrapper = TextWrapper::new();
+        let mut cursor = 0;
+        for m in moves {
+            let dir = match m {
+                0 => Dir::Up,
+                1 => Dir::Down,
+                2 => Dir::Left,
+                _ => Dir::Right,
+            };
+            match wrapper.move_cursor(&text, width, cursor, dir) {
+                Some(next) => {
+                    prop_assert!(next < text.len());
+                    cursor = next;
+                }
+                None => prop_assert!(text.is_empty() || cursor < text.len()),
+            }
+        }
+    }
+
+    #[test]
+    fn rewrapping_at_a_new_width_matches_a_fresh_wrap(s in text(), first in 0u16..120, second in 0u16..120) {
+        let text = GlyphString::new(s);
+        let wrapper = TextWrapper::new();
+        all_lines(&wrapper, &text, first);
+        prop_assert_eq!(
+            all_lines(&wrapper, &text, second),
+            all_lines(&TextWrapper::new(), &text, second)
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// Practice typing by copying out books.
+#[derive(Parser)]
+#[command(name = "scrivenwright", version)]
+pub struct Cli {
+    /// The directory which books, their history and settings are kept in.
+    /// Defaults to the platform's data and config directories.
+    #[arg(long, global = true, value_name = "DIR", env = "SCRIVENWRIGHT_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Continue typing a book from where you left off. Without a book, the
+    /// library is shown to choose one from, including the bundled samples.
+    Practice {
+        book: Option<String>,
+        #[command(flatten)]
+        display: Display,
+    },
+    /// List the books in the library and how far through each you are.
+    List,
+    /// Add a .txt book to the library, or merge in a bundle exported from
+    /// either frontend.
+    Import {
+        file: PathBuf,
+        /// The title to give the book. Defaults to the file name.
+        #[arg(long)]
+        title: Option<String>,
+        /// Also replace the settings with those in the bundle.
+        #[arg(long)]
+        settings: bool,
+    },
+    /// Show statistics for a book.
+    Stats { book: String },
+    /// Export history for one book, or all books.
+    Export {
+        #[arg(long)]
+        book: Option<String>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// The directory to write CSV or JSON files to, or the file to write
+        /// a bundle to.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Forget all practice of a book, starting it again from the beginning.
+    Reset {
+        book: String,
+        /// Don't ask for confirmation.
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Move to a position in a book, given as a percentage.
+    Goto {
+        book: String,
+        #[arg(allow_negative_numbers = true)]
+        percent: f64,
+    },
+    /// Choose the colour theme, or list the themes if none is given. Custom
+    /// themes are TOML files in the themes directory of the config directory.
+    Theme {
+        /// A theme's name, or the path of a theme file.
+        theme: Option<String>,
+    },
+}
+
+/// Overrides for how the text is displayed, which apply to this session only.
+#[derive(Args)]
+pub struct Display {
+    /// The percentage of the terminal's width which the text fills.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(10..=100))]
+    pub width: Option<u16>,
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+    /// A theme's name, or the path of a theme file.
+    #[arg(long)]
+    pub theme: Option<String>,
+    /// Seconds without typing before a test is paused, or 0 to never pause.
+    #[arg(long)]
+    pub idle: Option<u32>,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum Mode {
+    Normal,
+    Full,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// A bundle which can be imported by either frontend.
+    Bundle,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(["scrivenwright"].iter().chain(args))
+    }
+
+    #[test]
+    fn cli_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn practice_takes_display_overrides() {
+        let cli = parse(&["practice", "emma", "--width", "80", "--mode", "full"]).unwrap();
+        let Command::Practice { book, display } = cli.command else {
+            panic!("not practice");
+        };
+        assert_eq!(book.as_deref(), Some("emma"));
+        assert_eq!(display.width, Some(80));
+        assert!(matches!(display.mode, Some(Mode::Full)));
+        assert!(display.theme.is_none() && display.idle.is_none());
+    }
+
+    #[test]
+    fn width_is_limited() {
+        assert!(parse(&["practice", "--width", "9"]).is_err());
+        assert!(parse(&["practice", "--width", "101"]).is_err());
+        assert!(parse(&["practice", "--width", "100"]).is_ok());
+    }
+
+    #[test]
+    fn data_dir_can_follow_the_command() {
+        let cli = parse(&["list", "--data-dir", "/tmp/books"]).unwrap();
+        assert_eq!(cli.data_dir, Some(PathBuf::from("/tmp/books")));
+    }
+
+    #[test]
+    fn export_defaults_to_csv() {
+        let cli = parse(&["export"]).unwrap();
+        let Command::Export {
+            book,
+            format,
+            output,
+        } = cli.command
+        else {
+            panic!("not export");
+        };
+        assert!(book.is_none() && output.is_none());
+        assert!(format == ExportFormat::Csv);
+
+        let cli = parse(&["export", "--format", "bundle", "-o", "out.json"]).unwrap();
+        let Command::Export { format, output, .. } = cli.command else {
+            panic!("not export");
+        };
+        assert!(format == ExportFormat::Bundle);
+        assert_eq!(output, Some(PathBuf::from("out.json")));
+    }
+
+    #[test]
+    fn goto_accepts_any_number() {
+        // The range is checked by the command, so it can explain it.
+        let cli = parse(&["goto", "emma", "-5"]).unwrap();
+        assert!(matches!(cli.command, Command::Goto { percent, .. } if percent == -5.0));
+        assert!(parse(&["goto", "emma", "half"]).is_err());
+    }
+
+    #[test]
+    fn reset_and_import_flags() {
+        let cli = parse(&["reset", "emma", "-y"]).unwrap();
+        assert!(matches!(cli.command, Command::Reset { yes: true, .. }));
+        let cli = parse(&["import", "b.json", "--settings"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Import {
+                settings: true,
+                title: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn a_command_is_required() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["stats"]).is_err());
+    }
+}
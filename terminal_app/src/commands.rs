@@ -0,0 +1,274 @@
+use crate::cli::ExportFormat;
+use crate::library::Library;
+use crate::storage;
+use chrono::Utc;
+use scrivenwright::app::{AppResult, PlatformAdapter};
+use scrivenwright::bundle::{BookBundle, Bundle};
+use scrivenwright::export::{self, Format, History};
+use scrivenwright::stats::Summary;
+use scrivenwright::text::TestResult;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+fn require_book(book_title: &str) -> AppResult<()> {
+    if storage::list_books()?.iter().any(|b| b == book_title) {
+        Ok(())
+    } else {
+        Err(format!(
+            "There is no book named {}. Run `list` to see the library.",
+            book_title
+        )
+        .into())
+    }
+}
+
+fn summary(book_title: &str) -> AppResult<Summary> {
+    let text = storage::load_book(book_title)?;
+    let tests = storage::load_tests(book_title)?.records;
+    Ok(Summary::new(&text, &tests))
+}
+
+pub fn list() -> AppResult<()> {
+    let texts = Library.get_texts();
+    if texts.is_empty() {
+        println!("The library is empty. Add a book with `import <file>`.");
+    }
+    for text in texts {
+        let progress = match text.percent_complete() {
+            Some(percent) => format!("{:>6.2}%", percent),
+            None => " sample".to_string(),
+        };
+        match text.author {
+            Some(author) => println!("{}  {} by {}", progress, text.name, author),
+            None => println!("{}  {}", progress, text.name),
+        }
+    }
+    Ok(())
+}
+
+pub fn import(file: &Path, title: Option<String>, with_settings: bool) -> AppResult<()> {
+    let contents =
+        fs::read_to_string(file).map_err(|e| format!("Couldn't read {}: {}", file.display(), e))?;
+    if file.extension().is_some_and(|e| e == "json") {
+        return import_bundle(&contents, with_settings);
+    }
+
+    let title = match title {
+        Some(title) => title,
+        None => file
+            .file_stem()
+            .ok_or("Please give the book a title with --title")?
+            .to_string_lossy()
+            .into_owned(),
+    };
+    if storage::list_books()?.contains(&title) {
+        return Err(format!("There is already a book named {}", title).into());
+    }
+    storage::import_book(&title, &storage::normalize(contents))?;
+    println!("Added {}", title);
+    Ok(())
+}
+
+/// Merges a bundle into the library. Every book is merged before anything is
+/// written, so a bundle which can't be imported leaves the library as it was.
+fn import_bundle(json: &str, with_settings: bool) -> AppResult<()> {
+    let bundle = Bundle::from_json(json)?;
+    let existing = storage::list_books()?;
+    let mut merged = Vec::new();
+    for book in bundle.books {
+        let title = book.title.clone();
+        let text = book.text.clone();
+        let (local_text, mut tests, mut keypresses) = if existing.contains(&title) {
+            let tests = storage::load_tests(&title)?;
+            let keypresses = storage::load_keypresses(&title)?;
+            // Unreadable lines are kept when the logs are replaced.
+            for error in tests.errors.iter().chain(&keypresses.errors) {
+                eprintln!(
+                    "warning: {} has an unreadable record, which was left as it was: {}",
+                    title, error
+                );
+            }
+            (
+                Some(storage::load_book(&title)?),
+                tests.records,
+                keypresses.records,
+            )
+        } else {
+            (None, Vec::new(), Vec::new())
+        };
+
+        book.merge_into(local_text.as_deref(), &mut tests, &mut keypresses)
+            .map_err(|e| format!("Nothing was imported, as {}", e))?;
+        merged.push((
+            title,
+            local_text.is_none().then_some(text),
+            tests,
+            keypresses,
+        ));
+    }
+
+    for (title, new_text, tests, keypresses) in merged {
+        if let Some(text) = new_text {
+            storage::import_book(&title, &text)?;
+        }
+        storage::create_book_dir(&title);
+        storage::replace_tests(&title, &tests)?;
+        storage::replace_keypresses(&title, &keypresses)?;
+        println!("Imported {}, which now has {} tests", title, tests.len());
+    }
+    match bundle.settings {
+        Some(settings) if with_settings => {
+            storage::save_settings(&settings)?;
+            println!("Imported the settings");
+        }
+        Some(_) => println!("Left the settings as they were. Pass --settings to import them."),
+        None if with_settings => return Err("The bundle has no settings to import".into()),
+        None => {}
+    }
+    Ok(())
+}
+
+pub fn stats(book_title: &str) -> AppResult<()> {
+    require_book(book_title)?;
+    let summary = summary(book_title)?;
+    println!("{}", book_title);
+    println!(
+        "  Progress:    {:.2}% ({} of {} characters)",
+        summary.percent_complete(),
+        summary.position,
+        summary.length
+    );
+    println!(
+        "  Tests:       {} ({} succeeded)",
+        summary.tests, summary.succeeded
+    );
+    println!("  Average WPM: {:.1}", summary.average_wpm);
+    println!("  Best WPM:    {:.1}", summary.best_wpm);
+    Ok(())
+}
+
+pub fn export(
+    book_title: Option<String>,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> AppResult<()> {
+    let titles = match book_title {
+        Some(title) => {
+            require_book(&title)?;
+            vec![title]
+        }
+        None => storage::list_books()?,
+    };
+
+    let mut loaded = Vec::new();
+    for title in titles {
+        let text = storage::load_book(&title)?;
+        let tests = storage::load_tests(&title)?.records;
+        let keypresses = storage::load_keypresses(&title)?.records;
+        loaded.push((title, text, tests, keypresses));
+    }
+
+    let format = match format {
+        ExportFormat::Csv => Format::Csv,
+        ExportFormat::Json => Format::Json,
+        ExportFormat::Bundle => {
+            let path = output.unwrap_or("scrivenwright-bundle.json".into());
+            let books = loaded
+                .into_iter()
+                .map(|(title, text, tests, keypresses)| {
+                    BookBundle::new(title, text, tests, keypresses)
+                })
+                .collect();
+            let bundle = Bundle::new(Some(storage::load_settings()?), books);
+            fs::write(&path, bundle.to_json()?)?;
+            println!(
+                "Exported {} books to {}",
+                bundle.books.len(),
+                path.display()
+            );
+            return Ok(());
+        }
+    };
+
+    let books = loaded
+        .iter()
+        .map(|(title, text, tests, keypresses)| History {
+            title,
+            text,
+            tests,
+            keypresses,
+        })
+        .collect::<Vec<_>>();
+    let dir = output.unwrap_or_default();
+    let tests_path = dir.join(format!("scrivenwright-tests.{}", format.extension()));
+    export::write_tests(
+        &books,
+        format,
+        &mut BufWriter::new(File::create(&tests_path)?),
+    )?;
+    let keypresses_path = dir.join(format!("scrivenwright-keypresses.{}", format.extension()));
+    export::write_keypresses(
+        &books,
+        format,
+        &mut BufWriter::new(File::create(&keypresses_path)?),
+    )?;
+    println!(
+        "Exported to {} and {}",
+        tests_path.display(),
+        keypresses_path.display()
+    );
+    Ok(())
+}
+
+pub fn reset(book_title: &str, yes: bool) -> AppResult<()> {
+    require_book(book_title)?;
+    if !yes {
+        print!(
+            "This will forget all practice of {}. Continue? [y/N] ",
+            book_title
+        );
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            return Err("Reset cancelled".into());
+        }
+    }
+    storage::reset_book(book_title)?;
+    println!("Reset {}", book_title);
+    Ok(())
+}
+
+pub fn goto(book_title: &str, percent: f64) -> AppResult<()> {
+    require_book(book_title)?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err("The position must be a percentage between 0 and 100".into());
+    }
+    let length = summary(book_title)?.length;
+    let index = ((length as f64 * percent / 100.0) as usize).min(length);
+    storage::create_book_dir(book_title);
+    storage::save_test(book_title, &TestResult::bookmark(index, Utc::now()))?;
+    println!("Moved to {:.2}% of {}", percent, book_title);
+    Ok(())
+}
+
+pub fn theme(theme: Option<String>) -> AppResult<()> {
+    let mut settings = storage::load_settings()?;
+    let Some(name) = theme else {
+        let current = settings.theme.builtin_name();
+        for name in storage::list_themes()? {
+            let marker = if current == Some(name.as_str()) {
+                "*"
+            } else {
+                " "
+            };
+            println!("{} {}", marker, name);
+        }
+        return Ok(());
+    };
+    settings.theme = storage::load_theme(&name)?;
+    storage::save_settings(&settings)?;
+    println!("Now using {}", name);
+    Ok(())
+}
@@ -1,16 +1,22 @@
 use crossterm::event::{
-    self, Event as CrosstermEvent, KeyCode as CK, KeyEvent, KeyModifiers as CM,
+    self, Event as CrosstermEvent, KeyCode as CK, KeyEvent, KeyModifiers as CM, MouseButton,
+    MouseEventKind as CMK,
 };
 use scrivenwright::app::AppResult;
-use scrivenwright::handler::{KeyCode as K, KeyDown, KeyModifiers as M};
+use scrivenwright::handler::{KeyCode as K, KeyDown, KeyModifiers as M, MouseEvent};
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum Event {
     Key(KeyDown),
+    /// Text pasted into the terminal, which arrives all at once.
+    Paste(String),
+    Mouse(MouseEvent),
     Resize(u16, u16),
+    /// Sent every tick, whether or not there was any input.
+    Tick,
 }
 
 fn to_key_down(event: KeyEvent) -> KeyDown {
@@ -31,10 +37,21 @@ fn to_key_down(event: KeyEvent) -> KeyDown {
     KeyDown { code, mods }
 }
 
+fn to_mouse_event(event: event::MouseEvent) -> Option<MouseEvent> {
+    match event.kind {
+        CMK::ScrollUp => Some(MouseEvent::ScrollUp),
+        CMK::ScrollDown => Some(MouseEvent::ScrollDown),
+        CMK::Down(MouseButton::Left) => Some(MouseEvent::Click {
+            column: event.column,
+            row: event.row,
+        }),
+        _ => None,
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct EventHandler {
-    sender: mpsc::Sender<Event>,
     receiver: mpsc::Receiver<Event>,
     handler: thread::JoinHandle<()>,
 }
@@ -44,37 +61,55 @@ impl EventHandler {
         let tick_rate = Duration::from_millis(tick_rate);
         let (sender, receiver) = mpsc::channel();
         let handler = {
-            let sender: mpsc::Sender<Event> = sender.clone();
             thread::spawn(move || {
                 let mut last_tick = Instant::now();
                 loop {
                     let timeout = tick_rate
                         .checked_sub(last_tick.elapsed())
-                        .unwrap_or(tick_rate);
+                        .unwrap_or(Duration::ZERO);
 
-                    if event::poll(timeout).expect("no events available") {
-                        match event::read().expect("unable to read event") {
-                            CrosstermEvent::Key(e) => sender.send(Event::Key(to_key_down(e))),
-                            CrosstermEvent::Resize(w, h) => sender.send(Event::Resize(w, h)),
-                            _ => Ok(()),
+                    // Stop if the terminal can't be read from, or the receiver
+                    // has gone, which disconnects the channel.
+                    match event::poll(timeout) {
+                        Ok(true) => {
+                            let sent = match event::read() {
+                                Ok(CrosstermEvent::Key(e)) => {
+                                    sender.send(Event::Key(to_key_down(e)))
+                                }
+                                Ok(CrosstermEvent::Paste(text)) => sender.send(Event::Paste(text)),
+                                Ok(CrosstermEvent::Mouse(e)) => match to_mouse_event(e) {
+                                    Some(e) => sender.send(Event::Mouse(e)),
+                                    None => Ok(()),
+                                },
+                                Ok(CrosstermEvent::Resize(w, h)) => {
+                                    sender.send(Event::Resize(w, h))
+                                }
+                                Ok(_) => Ok(()),
+                                Err(_) => break,
+                            };
+                            if sent.is_err() {
+                                break;
+                            }
                         }
-                        .expect("failed to send terminal event")
+                        Ok(false) => {}
+                        Err(_) => break,
                     }
 
                     if last_tick.elapsed() >= tick_rate {
+                        if sender.send(Event::Tick).is_err() {
+                            break;
+                        }
                         last_tick = Instant::now();
                     }
                 }
             })
         };
-        Self {
-            sender,
-            receiver,
-            handler,
-        }
+        Self { receiver, handler }
     }
 
-    pub fn next(&self) -> Event {
-        self.receiver.recv().unwrap()
+    pub fn next(&self) -> AppResult<Event> {
+        self.receiver
+            .recv()
+            .map_err(|_| "Stopped receiving input from the terminal".into())
     }
 }
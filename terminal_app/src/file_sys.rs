@@ -1,16 +1,160 @@
+use chrono::Utc;
 use regex::Regex;
 use scrivenwright::app::AppResult;
+use scrivenwright::bundle::{self, Timestamped};
+use scrivenwright::schema::{self, DecodedLog, Versioned};
+use scrivenwright::settings::Settings;
 use scrivenwright::text::{KeyPress, TestResult};
+use scrivenwright::theme::{Theme, BUILTIN_THEMES};
 use std::{
+    collections::hash_map::RandomState,
     fs,
-    io::{BufRead, BufReader, Read, Write},
-    path::PathBuf,
+    hash::{BuildHasher, Hasher},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 static SW_DIR: &str = "scrivenwright";
+static TESTS: &str = "tests";
+static KEYPRESSES: &str = "keypresses";
+static SETTINGS: &str = "settings.json";
+/// Written into the data directory when a legacy library is copied into it.
+static MIGRATED_FROM: &str = ".migrated_from";
 
-fn sw_dir() -> PathBuf {
-    dirs::home_dir().expect("no home dir").join(SW_DIR)
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+
+/// Where the library is kept, and where settings are kept.
+struct Dirs {
+    data: PathBuf,
+    config: PathBuf,
+}
+
+impl Dirs {
+    /// Follows the platform's conventions, which on Linux are
+    /// `$XDG_DATA_HOME/scrivenwright` and `$XDG_CONFIG_HOME/scrivenwright`.
+    fn standard() -> AppResult<Self> {
+        let data = dirs::data_dir().ok_or("Couldn't find a data directory")?;
+        let config = dirs::config_dir().ok_or("Couldn't find a config directory")?;
+        Ok(Self {
+            data: data.join(SW_DIR),
+            config: config.join(SW_DIR),
+        })
+    }
+
+    /// Keeps everything in one directory.
+    fn overridden(dir: PathBuf) -> Self {
+        Self {
+            data: dir.clone(),
+            config: dir,
+        }
+    }
+}
+
+/// Chooses the directories which the library and settings are kept in, and
+/// creates them. `data_dir` overrides the standard directories, and is used
+/// for both. Without an override, a library in the directory used by older
+/// versions, `~/scrivenwright`, is copied into the standard directories.
+pub fn init(data_dir: Option<PathBuf>) -> AppResult<()> {
+    let dirs = match data_dir {
+        Some(dir) => Dirs::overridden(dir),
+        None => {
+            let dirs = Dirs::standard()?;
+            if let Some(home) = dirs::home_dir() {
+                migrate_legacy(&home.join(SW_DIR), &dirs)?;
+            }
+            dirs
+        }
+    };
+    fs::create_dir_all(&dirs.data)
+        .map_err(|e| format!("Couldn't create {}: {}", dirs.data.display(), e))?;
+    fs::create_dir_all(&dirs.config)
+        .map_err(|e| format!("Couldn't create {}: {}", dirs.config.display(), e))?;
+    let _ = DIRS.set(dirs);
+    Ok(())
+}
+
+/// Copies a library kept in `legacy` into the standard directories. The
+/// original is left where it is, as it may be in a synced folder, and
+/// removing it there would remove it from every machine.
+fn migrate_legacy(legacy: &Path, dirs: &Dirs) -> AppResult<()> {
+    if !legacy.is_dir() || legacy == dirs.data {
+        return Ok(());
+    }
+    let marker = dirs.data.join(MIGRATED_FROM);
+    if dirs.data.exists() {
+        if !marker.exists() {
+            eprintln!(
+                "warning: using the library in {}, and ignoring the one in {}, which older \
+                 versions used. Pass --data-dir {} to use that one instead.",
+                dirs.data.display(),
+                legacy.display(),
+                legacy.display()
+            );
+        }
+        return Ok(());
+    }
+
+    // Copy into a temporary directory first, so that an interrupted copy
+    // isn't mistaken for a library.
+    let mut partial = dirs.data.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let _ = fs::remove_dir_all(&partial);
+    copy_dir(legacy, &partial)
+        .and_then(|()| {
+            fs::write(
+                partial.join(MIGRATED_FROM),
+                legacy.to_string_lossy().as_ref(),
+            )
+        })
+        .and_then(|()| fs::rename(&partial, &dirs.data))
+        .map_err(|e| {
+            format!(
+                "Couldn't copy {} to {}: {}. Pass --data-dir {} to keep using it.",
+                legacy.display(),
+                dirs.data.display(),
+                e,
+                legacy.display()
+            )
+        })?;
+
+    let settings = dirs.data.join(SETTINGS);
+    if settings.exists() {
+        fs::create_dir_all(&dirs.config)?;
+        fs::rename(&settings, dirs.config.join(SETTINGS))?;
+    }
+    eprintln!(
+        "Copied the library from {} to {}. The original was left in place; pass \
+         --data-dir {} to keep using it instead, for example if it is synced.",
+        legacy.display(),
+        dirs.data.display(),
+        legacy.display()
+    );
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+fn dirs() -> &'static Dirs {
+    DIRS.get_or_init(|| Dirs::standard().expect("no standard directories"))
+}
+
+pub(crate) fn sw_dir() -> PathBuf {
+    dirs().data.clone()
 }
 
 fn book_file(book_title: &str) -> PathBuf {
@@ -21,12 +165,96 @@ fn book_dir(book_title: &str) -> PathBuf {
     sw_dir().join(book_title)
 }
 
-fn test_file(book_title: &str) -> PathBuf {
-    book_dir(book_title).join("tests.json")
+/// The log segment which this device appends to. Each device writes only to
+/// its own segment, so a book directory can be shared through a synced folder
+/// without concurrent appends conflicting.
+fn segment_file(book_title: &str, log: &str) -> PathBuf {
+    book_dir(book_title).join(format!("{}.{}.json", log, device_id()))
+}
+
+/// The single log file written before logs were split by device.
+fn legacy_file(book_title: &str, log: &str) -> PathBuf {
+    book_dir(book_title).join(format!("{}.json", log))
+}
+
+fn segment_files(book_title: &str, log: &str) -> AppResult<Vec<PathBuf>> {
+    let prefix = format!("{}.", log);
+    let mut segments = Vec::new();
+    let entries = match fs::read_dir(book_dir(book_title)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(segments),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        // Only `<log>.<device id>.json`, and not the legacy `<log>.json`.
+        let is_segment = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".json"))
+            .is_some_and(|id| !id.is_empty() && !id.contains('.'));
+        if is_segment {
+            segments.push(path);
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// Identifies this device. It is kept in `$XDG_STATE_HOME`, outside of the
+/// data directory, so that it isn't shared when that directory is synced.
+fn device_id() -> &'static str {
+    static DEVICE_ID: OnceLock<String> = OnceLock::new();
+    DEVICE_ID.get_or_init(|| {
+        let file = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|d| d.join(SW_DIR))
+            .unwrap_or_else(sw_dir)
+            .join("device_id");
+        if let Ok(id) = fs::read_to_string(&file) {
+            if !id.trim().is_empty() {
+                return id.trim().to_string();
+            }
+        }
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        let id = format!("{:016x}", hasher.finish());
+        let _ = file.parent().map(fs::create_dir_all);
+        let _ = fs::write(&file, &id);
+        id
+    })
 }
 
-fn keypress_file(book_title: &str) -> PathBuf {
-    book_dir(book_title).join("keypresses.json")
+fn settings_file() -> PathBuf {
+    dirs().config.join(SETTINGS)
+}
+
+fn themes_dir() -> PathBuf {
+    dirs().config.join("themes")
+}
+
+pub fn list_books() -> AppResult<Vec<String>> {
+    let mut books = Vec::new();
+    let entries = match fs::read_dir(sw_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(books),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "txt") {
+            if let Some(stem) = path.file_stem() {
+                books.push(stem.to_string_lossy().into_owned());
+            }
+        }
+    }
+    books.sort();
+    Ok(books)
 }
 
 pub fn create_book_dir(book_title: &str) {
@@ -34,7 +262,13 @@ pub fn create_book_dir(book_title: &str) {
 }
 
 pub fn load_book(book_title: &str) -> AppResult<String> {
-    let mut book: String = fs::read_to_string(book_file(book_title))?;
+    let book = fs::read_to_string(book_file(book_title))
+        .map_err(|e| format!("Couldn't read {}: {}", book_file(book_title).display(), e))?;
+    Ok(normalize(book))
+}
+
+/// Prepares the text of a book for typing.
+pub fn normalize(mut book: String) -> String {
     let rules: Vec<(Regex, &str)> = vec![
         //Remove carriage returns
         (Regex::new(r"\r").unwrap(), ""),
@@ -49,62 +283,231 @@ pub fn load_book(book_title: &str) -> AppResult<String> {
     for (re, replacement) in rules {
         book = re.replace_all(&book, replacement).into_owned();
     }
-    Ok(book)
+    book
 }
 
-pub fn load_tests(book_title: &str) -> AppResult<Vec<TestResult>> {
-    let file = fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(test_file(book_title))?;
+/// Adds a book, which has already been normalized, to the library.
+pub fn import_book(book_title: &str, text: &str) -> AppResult<()> {
+    fs::write(book_file(book_title), text)?;
+    create_book_dir(book_title);
+    Ok(())
+}
+
+pub fn load_settings() -> AppResult<Settings> {
+    match fs::read_to_string(settings_file()) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    let reader = BufReader::new(file);
-    let mut results = Vec::new();
+pub fn save_settings(settings: &Settings) -> AppResult<()> {
+    fs::write(settings_file(), serde_json::to_vec(settings)?)?;
+    Ok(())
+}
 
-    for line in reader.lines() {
-        let line = line?;
-        if line.trim().is_empty() {
-            continue;
-        }
-        if let Ok(test) = serde_json::from_str::<TestResult>(&line) {
-            results.push(test);
+/// Finds a theme by name. It can be a built-in theme, the name of a `.toml`
+/// file in the themes directory, or the path of a `.toml` file.
+pub fn load_theme(name: &str) -> AppResult<Theme> {
+    if let Some(theme) = Theme::builtin(name) {
+        return Ok(theme);
+    }
+    let named = themes_dir().join(format!("{}.toml", name));
+    let path = if named.exists() {
+        named
+    } else {
+        PathBuf::from(name)
+    };
+    let toml = fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "There is no built-in theme named {}, and {} couldn't be read: {}",
+            name,
+            path.display(),
+            e
+        )
+    })?;
+    Theme::from_toml(&toml).map_err(|e| format!("Couldn't load {}: {}", path.display(), e).into())
+}
+
+/// The built-in themes, followed by those in the themes directory.
+pub fn list_themes() -> AppResult<Vec<String>> {
+    let mut themes = Vec::new();
+    let entries = match fs::read_dir(themes_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(builtin_themes(themes)),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "toml") {
+            if let Some(stem) = path.file_stem() {
+                themes.push(stem.to_string_lossy().into_owned());
+            }
         }
     }
+    themes.sort();
+    Ok(builtin_themes(themes))
+}
+
+fn builtin_themes(custom: Vec<String>) -> Vec<String> {
+    BUILTIN_THEMES
+        .iter()
+        .map(|t| t.to_string())
+        .chain(custom)
+        .collect()
+}
 
-    Ok(results)
+pub fn load_tests(book_title: &str) -> AppResult<DecodedLog<TestResult>> {
+    load_log(book_title, TESTS)
 }
 
 pub fn save_test(book_title: &str, test: &TestResult) -> AppResult<()> {
-    let mut test_log = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(test_file(book_title))
-        .unwrap();
+    append_log(&segment_file(book_title, TESTS), std::slice::from_ref(test))
+}
+
+pub fn load_keypresses(book_title: &str) -> AppResult<DecodedLog<KeyPress>> {
+    load_log(book_title, KEYPRESSES)
+}
+
+pub fn save_keypresses(book_title: &str, keypresses: &[KeyPress]) -> AppResult<()> {
+    append_log(&segment_file(book_title, KEYPRESSES), keypresses)
+}
+
+/// Makes this device's segment contain all of `tests`. Records held by other
+/// devices' segments are left alone, and are merged back in on load.
+pub fn replace_tests(book_title: &str, tests: &[TestResult]) -> AppResult<()> {
+    replace_log(&segment_file(book_title, TESTS), tests)
+}
+
+pub fn replace_keypresses(book_title: &str, keypresses: &[KeyPress]) -> AppResult<()> {
+    replace_log(&segment_file(book_title, KEYPRESSES), keypresses)
+}
 
-    let bytes = serde_json::to_vec(test)?;
-    test_log.write_all(&bytes)?;
-    test_log.write_all(b"\n")?;
+/// Forgets all practice of a book. Every segment is backed up first.
+pub fn reset_book(book_title: &str) -> AppResult<()> {
+    for log in [TESTS, KEYPRESSES] {
+        for path in segment_files(book_title, log)? {
+            back_up(&path)?;
+            fs::remove_file(&path)?;
+        }
+    }
     Ok(())
 }
 
-pub fn load_keypresses(book_title: &str) -> AppResult<Vec<KeyPress>> {
-    let mut key_press_log = fs::OpenOptions::new()
-        .create(true)
-        .read(true)
-        .open(keypress_file(book_title))
-        .expect("Failed to open keypress file");
-    let mut string = String::new();
-    key_press_log.read_to_string(&mut string)?;
-    Ok(serde_json::from_str(&string).unwrap_or(Vec::new()))
+/// Loads every device's segment of a log, and merges them in timestamp order
+/// so that every device sees the same history.
+fn load_log<T: Versioned + Timestamped>(book_title: &str, log: &str) -> AppResult<DecodedLog<T>> {
+    let own = segment_file(book_title, log);
+    let legacy = legacy_file(book_title, log);
+    if legacy.exists() && !own.exists() {
+        fs::rename(&legacy, &own)?;
+    }
+
+    let mut merged = DecodedLog {
+        records: Vec::new(),
+        errors: Vec::new(),
+        outdated: false,
+    };
+    // Segments are merged with each other, but never with themselves, as a
+    // segment can hold identical records of its own, like the keypresses of
+    // a paste. Segments are sorted by name, so every device merges them in
+    // the same order.
+    let mut paths = segment_files(book_title, log)?;
+    // A legacy log can reappear beside this device's segment if a device
+    // which hasn't been updated shares the directory.
+    if legacy.exists() {
+        paths.push(legacy);
+    }
+    for (i, path) in paths.into_iter().enumerate() {
+        let segment = load_segment::<T>(&path, path == own)?;
+        merged.records = if i == 0 {
+            segment.records
+        } else {
+            bundle::merge_logs(&merged.records, &segment.records)
+        };
+        merged.errors.extend(segment.errors);
+    }
+    Ok(merged)
+}
+
+/// Loads a single segment. Only this device's segment is rewritten when it is
+/// outdated; other segments are upgraded in memory by the device which owns
+/// them.
+fn load_segment<T: Versioned>(path: &Path, owned: bool) -> AppResult<DecodedLog<T>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let log = schema::decode_log(&contents);
+    if log.outdated && owned {
+        rewrite_log(path, &log)?;
+    }
+    Ok(log)
 }
 
-pub fn save_keypresses(book_title: &str, keypresses: &Vec<KeyPress>) -> AppResult<()> {
-    let mut key_press_log = fs::OpenOptions::new()
+/// Rewrites a log in the current format, after backing up the original.
+/// Lines which couldn't be decoded are kept as they were.
+fn rewrite_log<T: Versioned>(path: &Path, log: &DecodedLog<T>) -> AppResult<()> {
+    back_up(path)?;
+    let mut contents = encode_log(&log.records)?;
+    for error in &log.errors {
+        contents.push_str(&error.content);
+        contents.push('\n');
+    }
+    write_atomically(path, &contents)
+}
+
+/// Replaces the records in a log, after backing up the original. Lines which
+/// couldn't be decoded are kept, as they can't have been merged.
+fn replace_log<T: Versioned>(path: &Path, records: &[T]) -> AppResult<()> {
+    let mut contents = encode_log(records)?;
+    match fs::read_to_string(path) {
+        Ok(old) => {
+            back_up(path)?;
+            for error in schema::decode_log::<T>(&old).errors {
+                contents.push_str(&error.content);
+                contents.push('\n');
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    write_atomically(path, &contents)
+}
+
+/// Writes a file in full, so that it is never left half written.
+fn write_atomically(path: &Path, contents: &str) -> AppResult<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+fn back_up(path: &Path) -> AppResult<()> {
+    let mut backup = path.as_os_str().to_owned();
+    // To the microsecond, so that backups made in quick succession are kept.
+    backup.push(format!(".{}.bak", Utc::now().format("%Y%m%d%H%M%S%6f")));
+    fs::copy(path, backup)?;
+    Ok(())
+}
+
+fn append_log<T: Versioned>(path: &Path, records: &[T]) -> AppResult<()> {
+    let mut log = fs::OpenOptions::new()
         .create(true)
-        .write(true)
-        .open(keypress_file(book_title))?;
-    let bytes = serde_json::to_vec(keypresses)?;
-    key_press_log.write(&bytes)?;
+        .append(true)
+        .open(path)?;
+
+    log.write_all(encode_log(records)?.as_bytes())?;
     Ok(())
 }
+
+fn encode_log<T: Versioned>(records: &[T]) -> AppResult<String> {
+    let mut contents = String::new();
+    for record in records {
+        contents.push_str(&schema::encode(record)?);
+        contents.push('\n');
+    }
+    Ok(contents)
+}
@@ -1,77 +1,257 @@
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crate::cli::{Cli, Command, Display, Mode};
+use clap::Parser;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
-use ratatui::backend::CrosstermBackend;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::Rect;
 use ratatui::Terminal;
-use scrivenwright::app::{App, AppResult};
-use scrivenwright::text::{KeyPress, OpenText, TestResult};
+use scrivenwright::app::{App, AppResult, PlatformAdapter, TICK_RATE_MS};
+use scrivenwright::schema;
+use scrivenwright::settings::{DEFAULT_TEXT_WIDTH_PERCENT, FULL_TEXT_WIDTH_PERCENT};
+use scrivenwright::text::{OpenText, Record};
+use std::io;
 use std::panic;
-use std::{env, io};
+use std::process::ExitCode;
 
+pub mod cli;
+pub mod commands;
 pub mod event;
 pub mod file_sys;
+pub mod library;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(not(feature = "sqlite"))]
+use crate::file_sys as storage;
+#[cfg(feature = "sqlite")]
+use crate::sqlite as storage;
 
 use crate::event::*;
 
-fn main() -> AppResult<()> {
-    let backend = CrosstermBackend::new(io::stderr());
-    let mut terminal = Terminal::new(backend)?;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = storage::init(cli.data_dir).and_then(|()| match cli.command {
+        Command::Practice { book, display } => practice(book, display),
+        Command::List => commands::list(),
+        Command::Import {
+            file,
+            title,
+            settings,
+        } => commands::import(&file, title, settings),
+        Command::Stats { book } => commands::stats(&book),
+        Command::Export {
+            book,
+            format,
+            output,
+        } => commands::export(book, format, output),
+        Command::Reset { book, yes } => commands::reset(&book, yes),
+        Command::Goto { book, percent } => commands::goto(&book, percent),
+        Command::Theme { theme } => commands::theme(theme),
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Opens a book for typing, adding it to the library first if it is one of
+/// the bundled samples. Also returns messages about tests which couldn't be
+/// read.
+fn open_text(book_title: &str) -> AppResult<(OpenText, Vec<String>)> {
+    if !library::add_sample(book_title)? {
+        return Err(format!(
+            "There is no book named {}. Run `list` to see the library.",
+            book_title
+        )
+        .into());
+    }
+    storage::create_book_dir(book_title);
+
+    let book_text = storage::load_book(book_title)?;
+
+    let test_log = storage::load_tests(book_title)?;
+    let warnings = test_log
+        .errors
+        .iter()
+        .map(|error| format!("Skipped unreadable test in {}: {}", book_title, error))
+        .collect();
 
-    let book_title = if let Some(arg_1) = env::args().collect::<Vec<_>>().get(1) {
-        arg_1.clone()
-    } else {
-        println!("Please provide the name of a book in the ~/scrivenwright directory");
-        return Ok(());
+    let title = book_title.to_string();
+    let save = move |record: &Record| match record {
+        Record::Test(test) => storage::save_test(&title, test),
+        Record::KeyPresses(keypresses) => storage::save_keypresses(&title, keypresses),
     };
+    Ok((OpenText::new(book_text, test_log.records, save), warnings))
+}
 
-    crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+/// Runs the typing interface for a book, or for the first book in the
+/// library with the library screen open.
+fn practice(book: Option<String>, display: Display) -> AppResult<()> {
+    let show_library = book.is_none();
+    let book_title = match book {
+        Some(book) => book,
+        None => library::Library
+            .get_texts()
+            .into_iter()
+            .next()
+            .map(|t| t.name)
+            .ok_or("The library is empty. Add a book with `import <file>`.")?,
+    };
+    let (mut text, warnings) = open_text(&book_title)?;
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    let theme = display
+        .theme
+        .as_deref()
+        .map(storage::load_theme)
+        .transpose()?;
+    // Everything which can fail is loaded before the terminal is taken over,
+    // so that an error doesn't leave it in raw mode.
+    let settings = storage::load_settings()?;
+
+    let backend = CrosstermBackend::new(io::stderr());
+    let mut terminal = Terminal::new(backend)?;
+    crossterm::execute!(
+        io::stderr(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     terminal::enable_raw_mode()?;
 
     let panic_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic| {
         let _ = terminal::disable_raw_mode();
-        let _ = crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture);
+        let _ = crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        );
         panic_hook(panic);
     }));
 
-    let _ = file_sys::create_book_dir(&book_title);
+    let mut app = App::new(library::Library);
+    app.settings = settings;
+    app.book_title = book_title;
+    if show_library {
+        app.open_library();
+    }
+    let overridden = display.width.is_some()
+        || display.mode.is_some()
+        || theme.is_some()
+        || display.idle.is_some();
+    if let Some(mode) = display.mode {
+        app.settings.full_text_width = matches!(mode, Mode::Full);
+        app.settings.text_width_percent = match mode {
+            Mode::Full => FULL_TEXT_WIDTH_PERCENT,
+            Mode::Normal => DEFAULT_TEXT_WIDTH_PERCENT,
+        };
+    }
+    if let Some(width) = display.width {
+        app.settings.text_width_percent = width;
+    }
+    if let Some(theme) = theme {
+        app.settings.theme = theme;
+    }
+    if let Some(idle) = display.idle {
+        app.settings.idle_secs = idle;
+    }
 
-    let book_text = file_sys::load_book(&book_title).expect("Failed to load book");
+    let result = run(&mut terminal, &mut app, &mut text);
 
-    let test_log = file_sys::load_tests(&book_title).expect("Failed to load tests");
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        io::stderr(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    terminal.show_cursor()?;
 
-    let save = move |test: TestResult, keypresses: Vec<KeyPress>| {
-        file_sys::save_test(&book_title, &test).unwrap();
-        file_sys::save_keypresses(&book_title, &keypresses).unwrap();
-    };
+    if !text.retry_saves() {
+        eprintln!(
+            "{}. These records couldn't be saved, and are printed here so they aren't lost:",
+            text.save_error().unwrap_or_default()
+        );
+        for record in text.unsaved() {
+            match record {
+                Record::Test(test) => eprintln!("{}", schema::encode(test)?),
+                Record::KeyPresses(keypresses) => {
+                    for keypress in keypresses {
+                        eprintln!("{}", schema::encode(keypress)?);
+                    }
+                }
+            }
+        }
+    }
+    if !overridden {
+        storage::save_settings(&app.settings)?;
+    }
+    result
+}
 
-    let mut text = OpenText::new(book_text, test_log, save);
-    let adapter = ();
+/// Replaces the open book with one chosen on the library screen, once the
+/// open book's tests have all been saved.
+fn switch_book(app: &mut App<library::Library>, text: &mut OpenText, book_title: String) {
+    if book_title == app.book_title {
+        return;
+    }
+    if !text.retry_saves() {
+        return;
+    }
+    match open_text(&book_title) {
+        Ok((opened, warnings)) => {
+            *text = opened;
+            app.book_title = book_title;
+            app.error = warnings.into_iter().next();
+        }
+        Err(e) => app.error = Some(e.to_string()),
+    }
+}
 
-    let mut app = App::new(adapter);
-    let mut width = terminal.size()?.width;
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App<library::Library>,
+    text: &mut OpenText,
+) -> AppResult<()> {
+    let mut screen = terminal.size()?;
 
-    let events = EventHandler::new(250);
+    let events = EventHandler::new(TICK_RATE_MS);
 
     terminal.hide_cursor()?;
     terminal.clear()?;
-    terminal.draw(|frame| app.render(&mut text, frame))?;
+    terminal.draw(|frame| app.render(text, frame))?;
 
     // Start the main loop.
     while app.running {
         // Handle events.
-        match events.next() {
+        match events.next()? {
             Event::Key(key_event) => {
-                app.handle_key_events(key_event, &mut text, width - 2);
+                app.handle_key_events(key_event, text, screen.width.saturating_sub(2));
+            }
+            Event::Paste(pasted) => {
+                app.handle_text(&pasted, text);
             }
-            Event::Resize(w, _) => {
-                width = w;
+            Event::Mouse(mouse_event) => {
+                app.handle_mouse_events(mouse_event, text, screen);
             }
+            Event::Resize(w, h) => {
+                screen = Rect::new(0, 0, w, h);
+            }
+            Event::Tick => app.tick(text),
+        }
+        if let Some(book_title) = app.chosen.take() {
+            switch_book(app, text, book_title);
         }
-        terminal.draw(|frame| app.render(&mut text, frame))?;
+        terminal.draw(|frame| app.render(text, frame))?;
     }
-
-    terminal::disable_raw_mode()?;
-    crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
     Ok(())
 }
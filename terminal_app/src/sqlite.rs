@@ -0,0 +1,325 @@
+use crate::file_sys;
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row, Transaction};
+use scrivenwright::app::AppResult;
+use scrivenwright::schema::DecodedLog;
+use scrivenwright::settings::Settings;
+use scrivenwright::text::{KeyPress, TestResult};
+use std::{fs, path::PathBuf};
+
+pub use crate::file_sys::{init, list_themes, load_theme, normalize};
+
+/// `MIGRATIONS[n]` upgrades the database from `user_version` `n` to `n + 1`.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE books (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL UNIQUE,
+        text TEXT NOT NULL
+    );
+    CREATE TABLE tests (
+        book_id INTEGER NOT NULL REFERENCES books(id),
+        succeeded INTEGER NOT NULL,
+        start_index INTEGER NOT NULL,
+        end_index INTEGER NOT NULL,
+        started INTEGER NOT NULL,
+        completed INTEGER NOT NULL
+    );
+    CREATE INDEX tests_by_book ON tests(book_id, completed);
+    CREATE TABLE keypresses (
+        book_id INTEGER NOT NULL REFERENCES books(id),
+        correct INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        time INTEGER NOT NULL
+    );
+    CREATE INDEX keypresses_by_book ON keypresses(book_id, time);
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+",
+    "
+    ALTER TABLE tests ADD COLUMN pauses TEXT NOT NULL DEFAULT '[]';
+",
+];
+
+fn db_file() -> PathBuf {
+    file_sys::sw_dir().join("scrivenwright.sqlite3")
+}
+
+fn open() -> AppResult<Connection> {
+    let mut conn = Connection::open(db_file())?;
+    let version: usize = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    if version < MIGRATIONS.len() {
+        let tx = conn.transaction()?;
+        for migration in &MIGRATIONS[version..] {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        tx.commit()?;
+    }
+    Ok(conn)
+}
+
+fn to_micros(time: DateTime<Utc>) -> i64 {
+    time.timestamp_micros()
+}
+
+/// Reads a timestamp from column `index` of a row.
+fn from_micros(row: &Row, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let micros = row.get(index)?;
+    Utc.timestamp_micros(micros)
+        .single()
+        .ok_or(rusqlite::Error::IntegralValueOutOfRange(index, micros))
+}
+
+fn book_id(conn: &Connection, book_title: &str) -> AppResult<Option<i64>> {
+    Ok(conn
+        .query_row("SELECT id FROM books WHERE title = ?1", [book_title], |r| {
+            r.get(0)
+        })
+        .optional()?)
+}
+
+/// Finds a book, importing it from the file system if needed.
+fn require_book_id(conn: &mut Connection, book_title: &str) -> AppResult<i64> {
+    if let Some(id) = book_id(conn, book_title)? {
+        return Ok(id);
+    }
+    import_legacy(conn, book_title)?;
+    book_id(conn, book_title)?.ok_or_else(|| format!("No book named {}", book_title).into())
+}
+
+pub fn list_books() -> AppResult<Vec<String>> {
+    let conn = open()?;
+    let mut stmt = conn.prepare("SELECT title FROM books ORDER BY title")?;
+    let mut books: Vec<String> = stmt
+        .query_map([], |r| r.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    // Books which haven't been imported yet are still available.
+    if let Ok(legacy) = file_sys::list_books() {
+        books.extend(legacy);
+        books.sort();
+        books.dedup();
+    }
+    Ok(books)
+}
+
+pub fn create_book_dir(_book_title: &str) {
+    let _ = fs::create_dir_all(file_sys::sw_dir());
+}
+
+/// Loads a book from the database. Books which have never been opened with
+/// this backend are imported from the file system first.
+pub fn load_book(book_title: &str) -> AppResult<String> {
+    let mut conn = open()?;
+    if book_id(&conn, book_title)?.is_none() {
+        import_legacy(&mut conn, book_title)?;
+    }
+    Ok(conn.query_row(
+        "SELECT text FROM books WHERE title = ?1",
+        [book_title],
+        |r| r.get(0),
+    )?)
+}
+
+/// Copies a book, along with its tests and keypresses, from the
+/// `~/scrivenwright/<book>` files into the database. Records which can't be
+/// read are reported, and left in the files.
+fn import_legacy(conn: &mut Connection, book_title: &str) -> AppResult<()> {
+    let text = file_sys::load_book(book_title)?;
+    let tests = file_sys::load_tests(book_title)?;
+    let keypresses = file_sys::load_keypresses(book_title)?;
+    for error in tests.errors.iter().chain(&keypresses.errors) {
+        eprintln!(
+            "warning: {} has an unreadable record, which wasn't moved into the database: {}",
+            book_title, error
+        );
+    }
+    let (tests, keypresses) = (tests.records, keypresses.records);
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO books (title, text) VALUES (?1, ?2)",
+        params![book_title, text],
+    )?;
+    let book_id = tx.last_insert_rowid();
+    for test in &tests {
+        insert_test(&tx, book_id, test)?;
+    }
+    for keypress in &keypresses {
+        insert_keypress(&tx, book_id, keypress)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Adds a book, which has already been normalized, to the library.
+pub fn import_book(book_title: &str, text: &str) -> AppResult<()> {
+    let conn = open()?;
+    conn.execute(
+        "INSERT INTO books (title, text) VALUES (?1, ?2)",
+        params![book_title, text],
+    )?;
+    Ok(())
+}
+
+pub fn load_settings() -> AppResult<Settings> {
+    let conn = open()?;
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'settings'",
+            [],
+            |r| r.get(0),
+        )
+        .optional()?;
+    match value {
+        Some(value) => Ok(serde_json::from_str(&value)?),
+        None => Ok(Settings::default()),
+    }
+}
+
+pub fn save_settings(settings: &Settings) -> AppResult<()> {
+    let conn = open()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('settings', ?1)",
+        [serde_json::to_string(settings)?],
+    )?;
+    Ok(())
+}
+
+pub fn load_tests(book_title: &str) -> AppResult<DecodedLog<TestResult>> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let mut stmt = conn.prepare(
+        "SELECT succeeded, start_index, end_index, started, completed, pauses
+         FROM tests WHERE book_id = ?1 ORDER BY rowid",
+    )?;
+    let records = stmt
+        .query_map([book_id], |r| {
+            Ok(TestResult {
+                succeeded: r.get(0)?,
+                start_index: r.get(1)?,
+                end_index: r.get(2)?,
+                started: from_micros(r, 3)?,
+                completed: from_micros(r, 4)?,
+                pauses: serde_json::from_str(&r.get::<_, String>(5)?).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(5, Type::Text, e.into())
+                })?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(DecodedLog {
+        records,
+        errors: Vec::new(),
+        outdated: false,
+    })
+}
+
+pub fn save_test(book_title: &str, test: &TestResult) -> AppResult<()> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let tx = conn.unchecked_transaction()?;
+    insert_test(&tx, book_id, test)?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn load_keypresses(book_title: &str) -> AppResult<DecodedLog<KeyPress>> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let mut stmt = conn
+        .prepare("SELECT correct, key, time FROM keypresses WHERE book_id = ?1 ORDER BY rowid")?;
+    let records = stmt
+        .query_map([book_id], |r| {
+            Ok(KeyPress {
+                correct: r.get(0)?,
+                key: r.get(1)?,
+                time: from_micros(r, 2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(DecodedLog {
+        records,
+        errors: Vec::new(),
+        outdated: false,
+    })
+}
+
+pub fn save_keypresses(book_title: &str, keypresses: &[KeyPress]) -> AppResult<()> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let tx = conn.unchecked_transaction()?;
+    for keypress in keypresses {
+        insert_keypress(&tx, book_id, keypress)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn replace_tests(book_title: &str, tests: &[TestResult]) -> AppResult<()> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM tests WHERE book_id = ?1", [book_id])?;
+    for test in tests {
+        insert_test(&tx, book_id, test)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn replace_keypresses(book_title: &str, keypresses: &[KeyPress]) -> AppResult<()> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM keypresses WHERE book_id = ?1", [book_id])?;
+    for keypress in keypresses {
+        insert_keypress(&tx, book_id, keypress)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Forgets all practice of a book.
+pub fn reset_book(book_title: &str) -> AppResult<()> {
+    let mut conn = open()?;
+    let book_id = require_book_id(&mut conn, book_title)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM tests WHERE book_id = ?1", [book_id])?;
+    tx.execute("DELETE FROM keypresses WHERE book_id = ?1", [book_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn insert_test(tx: &Transaction, book_id: i64, test: &TestResult) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO tests
+         (book_id, succeeded, start_index, end_index, started, completed, pauses)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            book_id,
+            test.succeeded,
+            test.start_index,
+            test.end_index,
+            to_micros(test.started),
+            to_micros(test.completed),
+            serde_json::to_string(&test.pauses)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_keypress(tx: &Transaction, book_id: i64, keypress: &KeyPress) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO keypresses (book_id, correct, key, time) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            book_id,
+            keypress.correct,
+            keypress.key,
+            to_micros(keypress.time),
+        ],
+    )?;
+    Ok(())
+}
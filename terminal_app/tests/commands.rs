@@ -0,0 +1,144 @@
+//! The commands other than `practice`, and their exit codes.
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+
+/// A sandbox with a book of 100 glyphs.
+fn sandbox_with_book() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let book = sandbox.write("words.txt", &"word ".repeat(20));
+    sandbox.ok(&["import", book.to_str().unwrap()]);
+    sandbox
+}
+
+fn stderr(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn failures_exit_with_an_error() {
+    let sandbox = sandbox_with_book();
+    let output = sandbox.run(&["stats", "missing"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error: There is no book named missing"));
+
+    // Usage errors are reported by the parser.
+    let output = sandbox.run(&["stats"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = sandbox.run(&["goto", "words", "half"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn importing_a_book_twice_fails() {
+    let sandbox = sandbox_with_book();
+    let book = sandbox.home.path().join("words.txt");
+    let output = sandbox.run(&["import", book.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("already a book named words"));
+    let output = sandbox.ok(&["import", book.to_str().unwrap(), "--title", "again"]);
+    assert_eq!(output, "Added again\n");
+}
+
+#[test]
+fn goto_is_bounded() {
+    let sandbox = sandbox_with_book();
+    for percent in ["-1", "100.5"] {
+        let output = sandbox.run(&["goto", "words", percent]);
+        assert_eq!(output.status.code(), Some(1), "{}", percent);
+        assert!(stderr(&output).contains("between 0 and 100"));
+    }
+    sandbox.ok(&["goto", "words", "100"]);
+    assert!(sandbox
+        .ok(&["stats", "words"])
+        .contains("(100 of 100 characters)"));
+    sandbox.ok(&["goto", "words", "0"]);
+    assert!(sandbox
+        .ok(&["stats", "words"])
+        .contains("(0 of 100 characters)"));
+    sandbox.ok(&["goto", "words", "25.5"]);
+    assert!(sandbox
+        .ok(&["stats", "words"])
+        .contains("(25 of 100 characters)"));
+}
+
+#[test]
+fn reset_asks_first() {
+    let sandbox = sandbox_with_book();
+    sandbox.ok(&["goto", "words", "50"]);
+
+    // Without an answer nothing is reset.
+    let output = sandbox.run(&["reset", "words"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Reset cancelled"));
+    assert!(sandbox.ok(&["stats", "words"]).contains("(50 of 100"));
+
+    let mut child = sandbox
+        .command(&["reset", "words"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"y\n").unwrap();
+    assert!(child.wait_with_output().unwrap().status.success());
+    assert!(sandbox.ok(&["stats", "words"]).contains("(0 of 100"));
+
+    sandbox.ok(&["goto", "words", "50"]);
+    assert_eq!(sandbox.ok(&["reset", "words", "--yes"]), "Reset words\n");
+    assert!(sandbox.ok(&["stats", "words"]).contains("(0 of 100"));
+}
+
+#[test]
+fn export_writes_to_the_given_place() {
+    let sandbox = Sandbox::new();
+    let data = ".local/share/scrivenwright";
+    sandbox.write(format!("{}/words.txt", data), &"word ".repeat(20));
+    let tests = common::log(&[common::test_record(0, 5, 10)]);
+    sandbox.write(format!("{}/words/tests.json", data), &tests);
+    // Bookmarks aren't exported.
+    sandbox.ok(&["goto", "words", "50"]);
+
+    let dir = sandbox.home.path().join("out");
+    fs::create_dir(&dir).unwrap();
+    sandbox.ok(&["export", "-o", dir.to_str().unwrap()]);
+    let tests = fs::read_to_string(dir.join("scrivenwright-tests.csv")).unwrap();
+    assert_eq!(tests.lines().count(), 2);
+    assert!(tests.lines().nth(1).unwrap().starts_with("words,"));
+    assert!(dir.join("scrivenwright-keypresses.csv").exists());
+
+    let bundle = sandbox.home.path().join("books.json");
+    let output = sandbox.ok(&[
+        "export",
+        "--format",
+        "bundle",
+        "-o",
+        bundle.to_str().unwrap(),
+    ]);
+    assert!(output.starts_with("Exported 1 books to"));
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&bundle).unwrap()).unwrap();
+    assert_eq!(json["books"][0]["title"], "words");
+
+    // An export can be imported into another library.
+    let other = Sandbox::new();
+    other.ok(&["import", bundle.to_str().unwrap()]);
+    assert!(other.ok(&["stats", "words"]).contains("(50 of 100"));
+}
+
+// The SQLite backend keeps its settings in the database.
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn practice_fails_before_taking_over_the_terminal() {
+    let sandbox = sandbox_with_book();
+    sandbox.write(".config/scrivenwright/settings.json", "{not json");
+    let output = sandbox.run(&["practice", "words"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr(&output);
+    assert!(stderr.starts_with("error: "), "{}", stderr);
+    // The alternate screen was never entered.
+    assert!(!stderr.contains("\u{1b}[?1049h"));
+}
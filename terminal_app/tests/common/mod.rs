@@ -0,0 +1,96 @@
+//! Runs the binary with a temporary home directory, so that tests neither
+//! touch the real library nor see each other's.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+pub struct Sandbox {
+    pub home: TempDir,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self {
+            home: tempfile::tempdir().unwrap(),
+        }
+    }
+
+    /// The standard data directory, as the binary will find it.
+    pub fn data_dir(&self) -> PathBuf {
+        self.home.path().join(".local/share/scrivenwright")
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.home.path().join(".config/scrivenwright")
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_terminal_app"));
+        command
+            .args(args)
+            .env("HOME", self.home.path())
+            .env_remove("SCRIVENWRIGHT_DIR")
+            .stdin(Stdio::null());
+        for var in [
+            "XDG_DATA_HOME",
+            "XDG_CONFIG_HOME",
+            "XDG_STATE_HOME",
+            "XDG_CACHE_HOME",
+        ] {
+            command.env_remove(var);
+        }
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    /// Runs a command which should succeed, and returns what it printed.
+    pub fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Writes a file under the home directory, creating its parents.
+    pub fn write(&self, path: impl AsRef<Path>, contents: &str) -> PathBuf {
+        let path = self.home.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+/// A test record in the format the logs are written in.
+pub fn test_record(start_index: usize, end_index: usize, completed_secs: i64) -> String {
+    format!(
+        r#"{{"succeeded":true,"start_index":{},"end_index":{},"started":{},"completed":{},"pauses":[],"version":2}}"#,
+        start_index,
+        end_index,
+        (completed_secs - 1) * 1_000_000,
+        completed_secs * 1_000_000
+    )
+}
+
+pub fn keypress_record(key: &str, secs: i64) -> String {
+    format!(
+        r#"{{"correct":true,"key":"{}","time":{},"version":1}}"#,
+        key,
+        secs * 1_000_000
+    )
+}
+
+/// Joins records into the contents of a log.
+pub fn log(records: &[String]) -> String {
+    records.iter().map(|r| format!("{}\n", r)).collect()
+}
@@ -0,0 +1,81 @@
+//! Where the library and settings are kept.
+
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn standard_dirs_are_created() {
+    let sandbox = Sandbox::new();
+    sandbox.ok(&["list"]);
+    assert!(sandbox.data_dir().is_dir());
+    assert!(sandbox.config_dir().is_dir());
+}
+
+#[test]
+fn data_dir_overrides_the_standard_dirs() {
+    let sandbox = Sandbox::new();
+    let dir = sandbox.home.path().join("elsewhere");
+    let book = sandbox.write("book.txt", "Some text.");
+    sandbox.ok(&[
+        "--data-dir",
+        dir.to_str().unwrap(),
+        "import",
+        book.to_str().unwrap(),
+    ]);
+    assert!(dir.is_dir());
+    assert!(!sandbox.data_dir().exists());
+    assert!(!sandbox.ok(&["list"]).contains("book"));
+
+    let output = sandbox
+        .command(&["list"])
+        .env("SCRIVENWRIGHT_DIR", &dir)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("book"));
+}
+
+#[test]
+fn legacy_library_is_copied_and_left_in_place() {
+    let sandbox = Sandbox::new();
+    sandbox.write("scrivenwright/old.txt", "An old book.");
+    sandbox.write("scrivenwright/old/tests.json", "");
+    // As the first versions which saved settings wrote them.
+    let settings = r#"{"text_width_percent":70,"full_text_width":true}"#;
+    sandbox.write("scrivenwright/settings.json", settings);
+
+    let output = sandbox.run(&["list"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Copied the library"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("old"));
+
+    let legacy = sandbox.home.path().join("scrivenwright");
+    assert!(legacy.join("old.txt").exists());
+    assert!(legacy.join("old/tests.json").exists());
+    assert!(legacy.join("settings.json").exists());
+    assert!(sandbox.data_dir().join("old.txt").exists());
+    assert!(sandbox.data_dir().join("old").is_dir());
+    let copied = std::fs::read_to_string(sandbox.config_dir().join("settings.json")).unwrap();
+    assert_eq!(copied, settings);
+    assert!(!sandbox.data_dir().join("settings.json").exists());
+
+    // Only the first run copies it.
+    let output = sandbox.run(&["list"]);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn legacy_library_beside_a_standard_one_is_warned_about() {
+    let sandbox = Sandbox::new();
+    sandbox.write("scrivenwright/old.txt", "An old book.");
+    sandbox.write(".local/share/scrivenwright/new.txt", "A new book.");
+
+    for _ in 0..2 {
+        let output = sandbox.run(&["list"]);
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("warning"));
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("old"));
+    }
+    assert!(!sandbox.data_dir().join("old.txt").exists());
+    assert!(sandbox.home.path().join("scrivenwright/old.txt").exists());
+}
@@ -0,0 +1,110 @@
+//! Importing bundles exported by either frontend.
+
+mod common;
+
+use common::Sandbox;
+use std::path::PathBuf;
+
+fn export_bundle(sandbox: &Sandbox) -> PathBuf {
+    let bundle = sandbox.home.path().join("bundle.json");
+    sandbox.ok(&[
+        "export",
+        "--format",
+        "bundle",
+        "--output",
+        bundle.to_str().unwrap(),
+    ]);
+    bundle
+}
+
+/// The current theme, as marked by `theme`.
+fn theme(sandbox: &Sandbox) -> String {
+    let themes = sandbox.ok(&["theme"]);
+    let current = themes.lines().find(|l| l.starts_with('*')).unwrap();
+    current[2..].to_string()
+}
+
+#[test]
+fn a_bundle_which_cant_be_merged_changes_nothing() {
+    let from = Sandbox::new();
+    for (title, text) in [("aaa", "A new book."), ("words", "Their text.")] {
+        let book = from.write(format!("{}.txt", title), text);
+        from.ok(&["import", book.to_str().unwrap()]);
+        from.ok(&["goto", title, "50"]);
+    }
+    let bundle = export_bundle(&from);
+
+    let to = Sandbox::new();
+    let book = to.write("words.txt", "Our text.");
+    to.ok(&["import", book.to_str().unwrap()]);
+    let before = to.ok(&["stats", "words"]);
+
+    let output = to.run(&["import", bundle.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Nothing was imported"));
+    assert!(!to.ok(&["list"]).contains("aaa"));
+    assert_eq!(to.ok(&["stats", "words"]), before);
+}
+
+// Only the file logs can hold lines which can't be read.
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn unreadable_lines_are_kept_when_importing() {
+    let sandbox = Sandbox::new();
+    let data = ".local/share/scrivenwright";
+    sandbox.write(format!("{}/words.txt", data), &"word ".repeat(100));
+    let tests = common::log(&[common::test_record(0, 5, 10), "not json".to_string()]);
+    sandbox.write(format!("{}/words/tests.json", data), &tests);
+    let bundle = export_bundle(&sandbox);
+
+    let output = sandbox.run(&["import", bundle.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unreadable record"));
+
+    let segment = std::fs::read_dir(sandbox.data_dir().join("words"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| {
+            let name = p.file_name().unwrap().to_string_lossy();
+            name.starts_with("tests.") && name.ends_with(".json")
+        })
+        .unwrap();
+    let contents = std::fs::read_to_string(segment).unwrap();
+    assert_eq!(contents.lines().filter(|l| *l == "not json").count(), 1);
+    assert_eq!(contents.lines().count(), 2);
+}
+
+// Only the file logs are backed up.
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn every_import_keeps_a_backup() {
+    let sandbox = Sandbox::new();
+    let book = sandbox.write("words.txt", &"word ".repeat(100));
+    sandbox.ok(&["import", book.to_str().unwrap()]);
+    sandbox.ok(&["goto", "words", "50"]);
+    let bundle = export_bundle(&sandbox);
+    sandbox.ok(&["import", bundle.to_str().unwrap()]);
+    sandbox.ok(&["import", bundle.to_str().unwrap()]);
+
+    let backups = std::fs::read_dir(sandbox.data_dir().join("words"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("tests.") && name.ends_with(".bak"))
+        .count();
+    assert_eq!(backups, 2);
+}
+
+#[test]
+fn settings_are_only_imported_when_asked() {
+    let sandbox = Sandbox::new();
+    sandbox.ok(&["theme", "light"]);
+    let bundle = export_bundle(&sandbox);
+    sandbox.ok(&["theme", "solarized"]);
+
+    let output = sandbox.ok(&["import", bundle.to_str().unwrap()]);
+    assert!(output.contains("--settings"));
+    assert_eq!(theme(&sandbox), "solarized");
+
+    sandbox.ok(&["import", bundle.to_str().unwrap(), "--settings"]);
+    assert_eq!(theme(&sandbox), "light");
+}
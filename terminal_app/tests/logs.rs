@@ -0,0 +1,55 @@
+//! Logs in older formats, or damaged ones, are upgraded on load after being
+//! backed up.
+
+mod common;
+
+use common::{keypress_record, Sandbox};
+use std::fs;
+
+#[test]
+fn damaged_logs_are_backed_up_and_rewritten_once() {
+    let sandbox = Sandbox::new();
+    let data = ".local/share/scrivenwright";
+    sandbox.write(format!("{}/words.txt", data), &"word ".repeat(100));
+    // A short array written over a longer one, then appended to.
+    let damaged = format!(
+        "[{},{}]{{\"key\":\"x\",\"time\":5}}]{}\n{}\n",
+        keypress_record("a", 1),
+        keypress_record("b", 2),
+        keypress_record("c", 3),
+        keypress_record("d", 4)
+    );
+    sandbox.write(format!("{}/words/keypresses.json", data), &damaged);
+
+    let out = sandbox.home.path().join("out");
+    fs::create_dir_all(&out).unwrap();
+    let export = [
+        "export",
+        "--book",
+        "words",
+        "--format",
+        "json",
+        "--output",
+        out.to_str().unwrap(),
+    ];
+    for _ in 0..2 {
+        sandbox.ok(&export);
+        let exported = fs::read_to_string(out.join("scrivenwright-keypresses.ndjson")).unwrap();
+        assert_eq!(exported.lines().count(), 4);
+    }
+
+    let book_dir = sandbox.data_dir().join("words");
+    let files = fs::read_dir(&book_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    let backups = files
+        .iter()
+        .filter(|f| f.ends_with(".bak"))
+        .collect::<Vec<_>>();
+    assert_eq!(backups.len(), 1, "{:?}", files);
+    assert_eq!(
+        fs::read_to_string(book_dir.join(backups[0])).unwrap(),
+        damaged
+    );
+}
@@ -0,0 +1,90 @@
+//! Each device appends to its own segment of a book's logs, and loading
+//! merges every segment.
+
+mod common;
+
+use common::{keypress_record, log, test_record, Sandbox};
+use std::fs;
+
+/// Exports the tests and keypresses of "words" as newline delimited JSON.
+fn export(sandbox: &Sandbox) -> (String, String) {
+    let out = sandbox.home.path().join("out");
+    fs::create_dir_all(&out).unwrap();
+    sandbox.ok(&[
+        "export",
+        "--book",
+        "words",
+        "--format",
+        "json",
+        "--output",
+        out.to_str().unwrap(),
+    ]);
+    (
+        fs::read_to_string(out.join("scrivenwright-tests.ndjson")).unwrap(),
+        fs::read_to_string(out.join("scrivenwright-keypresses.ndjson")).unwrap(),
+    )
+}
+
+fn sandbox_with_segments() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let data = ".local/share/scrivenwright";
+    sandbox.write(format!("{}/words.txt", data), &"word ".repeat(100));
+    // Two tests finished by one paste, and the same keypress twice in a row.
+    let first = [test_record(0, 5, 10), test_record(5, 10, 10)];
+    let keys = [keypress_record("o", 9), keypress_record("o", 9)];
+    sandbox.write(format!("{}/words/tests.aaaa.json", data), &log(&first));
+    sandbox.write(format!("{}/words/keypresses.aaaa.json", data), &log(&keys));
+    // Another device which imported the first one's history, then went on.
+    let second = [
+        test_record(0, 5, 10),
+        test_record(5, 10, 10),
+        test_record(10, 15, 20),
+    ];
+    sandbox.write(format!("{}/words/tests.bbbb.json", data), &log(&second));
+    sandbox.write(format!("{}/words/keypresses.bbbb.json", data), &log(&keys));
+    sandbox
+}
+
+#[test]
+fn same_instant_records_are_kept() {
+    let sandbox = sandbox_with_segments();
+    let (tests, keypresses) = export(&sandbox);
+    let starts = tests
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["start_index"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(starts, [0, 5, 10]);
+    assert_eq!(keypresses.lines().count(), 2);
+    assert!(sandbox
+        .ok(&["stats", "words"])
+        .contains("(15 of 500 characters)"));
+}
+
+#[test]
+fn loading_is_deterministic() {
+    let sandbox = sandbox_with_segments();
+    let first = export(&sandbox);
+    for _ in 0..3 {
+        assert_eq!(export(&sandbox), first);
+    }
+}
+
+#[test]
+fn a_legacy_log_beside_segments_is_merged_once() {
+    let sandbox = Sandbox::new();
+    let data = ".local/share/scrivenwright";
+    sandbox.write(".local/state/scrivenwright/device_id", "cccc");
+    sandbox.write(format!("{}/words.txt", data), &"word ".repeat(100));
+    let own = [test_record(0, 5, 10)];
+    let legacy = log(&[test_record(0, 5, 10), test_record(5, 10, 20)]);
+    sandbox.write(format!("{}/words/tests.cccc.json", data), &log(&own));
+    let path = sandbox.write(format!("{}/words/tests.json", data), &legacy);
+
+    let (tests, _) = export(&sandbox);
+    let starts = tests
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["start_index"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(starts, [0, 5]);
+    assert_eq!(fs::read_to_string(path).unwrap(), legacy);
+}
@@ -0,0 +1,202 @@
+//! The SQLite backend, run against a database in a temporary home.
+
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use common::{keypress_record, log, test_record, Sandbox};
+use rusqlite::Connection;
+use std::fs;
+
+fn database(sandbox: &Sandbox) -> Connection {
+    Connection::open(sandbox.data_dir().join("scrivenwright.sqlite3")).unwrap()
+}
+
+fn count(conn: &Connection, table: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+        .unwrap()
+}
+
+/// Exports the tests and keypresses of "words" as newline delimited JSON.
+fn export(sandbox: &Sandbox) -> (String, String) {
+    let out = sandbox.home.path().join("out");
+    fs::create_dir_all(&out).unwrap();
+    sandbox.ok(&[
+        "export",
+        "--book",
+        "words",
+        "--format",
+        "json",
+        "--output",
+        out.to_str().unwrap(),
+    ]);
+    (
+        fs::read_to_string(out.join("scrivenwright-tests.ndjson")).unwrap(),
+        fs::read_to_string(out.join("scrivenwright-keypresses.ndjson")).unwrap(),
+    )
+}
+
+/// A book in the file system library, which the database hasn't seen yet.
+fn sandbox_with_legacy_book() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let data = ".local/share/scrivenwright";
+    sandbox.write(format!("{}/words.txt", data), &"word ".repeat(100));
+    let tests = [test_record(0, 5, 10), test_record(5, 10, 20)];
+    let keys = [keypress_record("w", 9), keypress_record("o", 9)];
+    sandbox.write(format!("{}/words/tests.json", data), &log(&tests));
+    sandbox.write(format!("{}/words/keypresses.json", data), &log(&keys));
+    sandbox
+}
+
+#[test]
+fn old_databases_are_migrated() {
+    let sandbox = Sandbox::new();
+    fs::create_dir_all(sandbox.data_dir()).unwrap();
+    let conn = database(&sandbox);
+    // The schema before tests had pauses.
+    conn.execute_batch(
+        "
+        CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT NOT NULL UNIQUE, text TEXT NOT NULL);
+        CREATE TABLE tests (book_id INTEGER NOT NULL, succeeded INTEGER NOT NULL,
+            start_index INTEGER NOT NULL, end_index INTEGER NOT NULL,
+            started INTEGER NOT NULL, completed INTEGER NOT NULL);
+        CREATE TABLE keypresses (book_id INTEGER NOT NULL, correct INTEGER NOT NULL,
+            key TEXT NOT NULL, time INTEGER NOT NULL);
+        CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO books (id, title, text) VALUES (1, 'words', 'word word word word');
+        INSERT INTO tests VALUES (1, 1, 0, 5, 0, 2000000);
+        PRAGMA user_version = 1;
+        ",
+    )
+    .unwrap();
+    drop(conn);
+
+    let stats = sandbox.ok(&["stats", "words"]);
+    assert!(stats.contains("(5 of 19 characters)"), "{}", stats);
+    let (tests, _) = export(&sandbox);
+    assert!(tests.contains(r#""duration_secs":2.0"#), "{}", tests);
+
+    let conn = database(&sandbox);
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(version, 2);
+    let pauses: String = conn
+        .query_row("SELECT pauses FROM tests", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(pauses, "[]");
+}
+
+#[test]
+fn legacy_books_are_imported_when_first_used() {
+    let sandbox = sandbox_with_legacy_book();
+    assert!(sandbox.ok(&["list"]).contains("words"));
+    assert!(sandbox
+        .ok(&["stats", "words"])
+        .contains("(10 of 500 characters)"));
+
+    let conn = database(&sandbox);
+    assert_eq!(count(&conn, "books"), 1);
+    assert_eq!(count(&conn, "tests"), 2);
+    assert_eq!(count(&conn, "keypresses"), 2);
+
+    // From then on the database is used, and the book isn't imported again.
+    fs::remove_dir_all(sandbox.data_dir().join("words")).unwrap();
+    let (tests, keypresses) = export(&sandbox);
+    assert_eq!(tests.lines().count(), 2);
+    assert_eq!(keypresses.lines().count(), 2);
+    assert_eq!(count(&conn, "books"), 1);
+}
+
+#[test]
+fn saved_tests_are_loaded_back() {
+    let sandbox = sandbox_with_legacy_book();
+    sandbox.ok(&["goto", "words", "50"]);
+    let (tests, _) = export(&sandbox);
+    let starts = tests
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["start_index"].clone())
+        .collect::<Vec<_>>();
+    // The bookmark isn't exported, but is where practice goes on from.
+    assert_eq!(starts, [0, 5]);
+    assert!(sandbox.ok(&["stats", "words"]).contains("(250 of 500"));
+}
+
+#[test]
+fn bundles_replace_the_history_they_merge_with() {
+    let sandbox = sandbox_with_legacy_book();
+    let before = export(&sandbox);
+    let bundle = sandbox.home.path().join("bundle.json");
+    sandbox.ok(&[
+        "export",
+        "--format",
+        "bundle",
+        "--output",
+        bundle.to_str().unwrap(),
+    ]);
+
+    // Importing into the same history changes nothing.
+    sandbox.ok(&["import", bundle.to_str().unwrap()]);
+    assert_eq!(export(&sandbox), before);
+
+    // Importing after a reset restores it.
+    sandbox.ok(&["reset", "words", "--yes"]);
+    let conn = database(&sandbox);
+    assert_eq!(count(&conn, "tests"), 0);
+    assert_eq!(count(&conn, "keypresses"), 0);
+    sandbox.ok(&["import", bundle.to_str().unwrap()]);
+    assert_eq!(export(&sandbox), before);
+}
+
+#[test]
+fn resetting_forgets_only_that_book() {
+    let sandbox = sandbox_with_legacy_book();
+    let other = sandbox.write("other.txt", "Some other text.");
+    sandbox.ok(&["import", other.to_str().unwrap()]);
+    sandbox.ok(&["goto", "other", "50"]);
+    sandbox.ok(&["reset", "words", "--yes"]);
+
+    assert!(sandbox
+        .ok(&["stats", "words"])
+        .contains("(0 of 500 characters)"));
+    assert!(sandbox
+        .ok(&["stats", "other"])
+        .contains("(8 of 16 characters)"));
+    let (tests, keypresses) = export(&sandbox);
+    assert!(tests.is_empty());
+    assert!(keypresses.is_empty());
+}
+
+#[test]
+fn unreadable_legacy_records_are_reported() {
+    let sandbox = sandbox_with_legacy_book();
+    let tests = log(&[test_record(0, 5, 10), "not json".to_string()]);
+    let path = sandbox.write(".local/share/scrivenwright/words/tests.json", &tests);
+
+    let output = sandbox.run(&["stats", "words"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unreadable record"), "{}", stderr);
+    assert!(stderr.contains("line 2"), "{}", stderr);
+    assert_eq!(count(&database(&sandbox), "tests"), 1);
+
+    // The file is left as it was, so nothing is lost.
+    let segments = fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|e| fs::read_to_string(e.unwrap().path()).unwrap())
+        .collect::<String>();
+    assert!(segments.contains("not json"));
+}
+
+#[test]
+fn bad_timestamps_name_their_column() {
+    let sandbox = sandbox_with_legacy_book();
+    sandbox.ok(&["stats", "words"]);
+    database(&sandbox)
+        .execute("UPDATE tests SET completed = ?1", [i64::MAX])
+        .unwrap();
+    let output = sandbox.run(&["stats", "words"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("index 4"), "{}", stderr);
+}
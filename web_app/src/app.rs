@@ -1,33 +1,108 @@
-use crate::TERMINAL;
+use crate::deep_link::{self, Link, Position};
+use crate::font::{self, Font, FONT_FAMILIES, FONT_SIZES};
+use crate::library::{self, Library};
+use crate::storage::{self, FailedSave};
+use crate::{soft_keyboard, TERMINAL};
 
-use gloo_storage::{LocalStorage, Storage};
-use js_sys::Function;
+use js_sys::{Array, Function};
 use ratatui::Frame;
-use scrivenwright::app::App;
-use scrivenwright::handler::{KeyCode as K, KeyDown, KeyModifiers as M};
-use scrivenwright::text::{KeyPress, OpenText, TestResult};
+use scrivenwright::app::{App, AppResult, TICK_RATE_MS};
+use scrivenwright::bundle::{BookBundle, Bundle};
+use scrivenwright::chapters;
+use scrivenwright::export::{self, Format, History};
+use scrivenwright::handler::{self, KeyCode as K, KeyDown, KeyModifiers as M};
+use scrivenwright::settings::Settings;
+use scrivenwright::text::{KeyPress, OpenText, Record, TestResult};
+use scrivenwright::theme::{Theme, BUILTIN_THEMES};
 use std::panic;
-use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{
+    Blob, File, FileReader, HtmlAnchorElement, HtmlInputElement, HtmlSelectElement, Url,
+};
 use yew::prelude::*;
 
+/// The choices of how long to wait before pausing a test, in seconds.
+const IDLE_SECS: &[u32] = &[0, 5, 10, 30, 60];
+
+fn idle_label(secs: u32) -> String {
+    if secs == 0 {
+        "never pause".to_string()
+    } else {
+        format!("pause after {}s", secs)
+    }
+}
+
 pub struct TermApp {
-    app: App<()>,
+    app: App<Library>,
     text: OpenText,
+    font: Font,
+    soft_keyboard: Option<HtmlInputElement>,
+    touch: Option<Touch>,
+    /// How far the wheel has turned, in pixels, since the book was last
+    /// scrolled by it.
+    wheel: f64,
+    /// Where the current test started when the browser history was last
+    /// updated.
+    linked_position: usize,
+    /// Whether exports hold every book, rather than only the open one.
+    export_all: bool,
+    /// Records of books other than the open one which couldn't be saved,
+    /// saved again on each tick.
+    failed_saves: Vec<FailedSave>,
+}
+
+/// A touch which is in progress on the terminal.
+struct Touch {
+    /// Where the touch was when the book was last scrolled.
+    last_y: f64,
+    scrolled: bool,
+}
+
+/// The logs which can be exported on their own. Each is downloaded with its
+/// own button, as browsers may block a second download started by one click.
+#[derive(Debug, Copy, Clone)]
+pub enum Log {
+    Tests,
+    KeyPresses,
 }
 
 #[derive(Debug)]
 pub enum TermAppMsg {
     Resized,
     KeyDown(KeyDown),
+    /// Text committed all at once, by an input method or on-screen keyboard.
+    Text(String),
+    Export(Log, Format),
+    SetExportAll(bool),
+    ExportBundle,
+    ImportBundle(String),
+    SetTheme(String),
+    LoadTheme(String),
+    SetFont(Font),
+    /// Seconds without typing before a test is paused.
+    SetIdle(u32),
+    TouchStart(f64),
+    TouchMove(f64),
+    TouchEnd,
+    /// The wheel turned by a distance in pixels.
+    Wheel(f64),
+    /// A click with a mouse, rather than a tap, at a cell of the terminal.
+    Click(u16, u16),
+    StorageError(String),
+    SaveFailed(FailedSave),
+    LinkChanged,
+    OpenLibrary,
+    Tick,
 }
 
-fn to_key_down(event: KeyboardEvent) -> KeyDown {
+fn to_key_down(event: &KeyboardEvent) -> KeyDown {
     let code = match event.key().as_str() {
         "Escape" => K::Esc,
         "ArrowUp" => K::Up,
         "ArrowDown" => K::Down,
         "ArrowRight" => K::Right,
         "ArrowLeft" => K::Left,
+        "Enter" => K::Char('\n'),
         s => {
             if s.len() == 1 {
                 K::Char(s.chars().next().unwrap())
@@ -65,6 +140,226 @@ impl TermApp {
     fn draw(&self, frame: &mut Frame<'_>) {
         self.app.render(&self.text, frame)
     }
+
+    /// Opens the book chosen on the library screen, if one was.
+    fn open_chosen(&mut self) {
+        if let Some(title) = self.app.chosen.take() {
+            if title != self.app.book_title {
+                self.open_book(title);
+            }
+        }
+    }
+
+    fn open_book(&mut self, title: String) {
+        self.load_book(title);
+        deep_link::push(&self.link());
+    }
+
+    fn load_book(&mut self, title: String) {
+        if let Err(e) = library::add_sample(&title) {
+            self.app.error = Some(e.to_string());
+            return;
+        }
+        if let Err(e) = storage::set_current_book(&title) {
+            self.app.error = Some(e.to_string());
+        }
+        let (text, warnings) = open_text(&title);
+        // Anything the open book couldn't save is saved again in the background.
+        let unsaved = self.text.unsaved().to_vec();
+        let previous = std::mem::replace(&mut self.app.book_title, title);
+        self.failed_saves
+            .extend(unsaved.into_iter().map(|record| FailedSave {
+                book_title: previous.clone(),
+                record,
+                error: String::new(),
+            }));
+        self.text = text;
+        if let Some(warning) = warnings.into_iter().next() {
+            self.app.error = Some(warning);
+        }
+        self.linked_position = self.position();
+    }
+
+    /// Saves the records of other books which couldn't be saved, oldest first.
+    fn retry_failed_saves(&mut self) {
+        while let Some(failed) = self.failed_saves.first() {
+            if let Err(e) = storage::save_record(&failed.book_title, &failed.record) {
+                self.app.error = Some(format!(
+                    "Failed to save progress in {}: {}",
+                    failed.book_title, e
+                ));
+                return;
+            }
+            self.failed_saves.remove(0);
+        }
+    }
+
+    /// Where the current test starts, or the end of the book if it is finished.
+    fn position(&self) -> usize {
+        self.text
+            .test
+            .map_or(self.text.text.len(), |t| t.start_index)
+    }
+
+    fn link(&self) -> Link {
+        Link {
+            book: self.app.book_title.clone(),
+            position: Some(Position::Glyph(self.position())),
+        }
+    }
+
+    /// Opens the book and position named by the URL hash.
+    fn follow_link(&mut self) {
+        let Some(link) = deep_link::current() else {
+            return;
+        };
+        let Some(title) = library::titles().into_iter().find(|t| link.names(t)) else {
+            self.app.error = Some(format!("There is no book called {}", link.book));
+            return;
+        };
+        if title != self.app.book_title {
+            self.load_book(title);
+        }
+        let index = match link.position {
+            Some(Position::Glyph(glyph)) => glyph,
+            Some(Position::Chapter(chapter)) => {
+                match chapters::chapter_start(self.text.chapters(), chapter) {
+                    Some(index) => index,
+                    None => {
+                        self.app.error = Some(format!(
+                            "{} has no chapter {}",
+                            self.app.book_title, chapter
+                        ));
+                        return;
+                    }
+                }
+            }
+            None => return,
+        };
+        // Following a link, including going back through the browser's
+        // history, doesn't save the move, so it can't lose progress.
+        if index != self.position() {
+            self.text.move_without_saving(index);
+        }
+        self.linked_position = self.position();
+    }
+
+    /// Adds the current position to the browser history once a test has been
+    /// completed.
+    fn update_link(&mut self) {
+        if self.position() != self.linked_position {
+            self.linked_position = self.position();
+            deep_link::push(&self.link());
+        }
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        TERMINAL
+            .term()
+            .backend_mut()
+            .set_background(theme.background);
+        self.app.settings.theme = theme;
+        self.save_settings();
+    }
+
+    fn save_settings(&mut self) {
+        if let Err(e) = storage::save_settings(&self.app.settings) {
+            self.app.error = Some(e.to_string());
+        }
+    }
+
+    /// Merges a bundle into the library. Every book is merged before anything
+    /// is written, so a bundle which can't be imported leaves the library as
+    /// it was.
+    fn import_bundle(&mut self, json: &str) -> AppResult<()> {
+        let bundle = Bundle::from_json(json)?;
+        let existing = storage::list_books();
+        let mut merged = Vec::new();
+        let mut unreadable = 0;
+        for book in bundle.books {
+            let title = book.title.clone();
+            let text = book.text.clone();
+            let local_text = storage::load_book(&title).filter(|_| existing.contains(&title));
+            let tests = storage::load_tests(&title);
+            let keypresses = storage::load_keypresses(&title);
+            // Unreadable lines are kept when the logs are replaced.
+            unreadable += tests.errors.len() + keypresses.errors.len();
+            let (mut tests, mut keypresses) = (tests.records, keypresses.records);
+
+            book.merge_into(local_text.as_deref(), &mut tests, &mut keypresses)
+                .map_err(|e| format!("Nothing was imported, as {}", e))?;
+            merged.push((
+                title,
+                local_text.is_none().then_some(text),
+                tests,
+                keypresses,
+            ));
+        }
+
+        let first = merged.first().map(|(title, ..)| title.clone());
+        for (title, new_text, tests, keypresses) in merged {
+            if let Some(text) = new_text {
+                storage::import_book(&title, &text)?;
+            }
+            storage::replace_tests(&title, &tests)?;
+            storage::replace_keypresses(&title, &keypresses)?;
+        }
+        if let Some(settings) = bundle.settings {
+            if confirm("Also replace your settings with those in the bundle?") {
+                TERMINAL
+                    .term()
+                    .backend_mut()
+                    .set_background(settings.theme.background);
+                self.app.settings = settings;
+                self.save_settings();
+            }
+        }
+        if let Some(title) = first {
+            self.open_book(title);
+        }
+        if unreadable > 0 {
+            self.app.error = Some(format!(
+                "{} unreadable records were left as they were",
+                unreadable
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Adds the book which new users start with, returning its title.
+#[cfg(feature = "samples")]
+fn add_default_book() -> Result<String, (String, Box<dyn std::error::Error>)> {
+    let sample = &scrivenwright::samples::SAMPLES[0];
+    storage::import_book(sample.title, &sample.text())
+        .map(|()| sample.title.to_string())
+        .map_err(|e| (sample.title.to_string(), e))
+}
+
+/// Adds the book which new users start with, returning its title.
+#[cfg(not(feature = "samples"))]
+fn add_default_book() -> Result<String, (String, Box<dyn std::error::Error>)> {
+    const DEFAULT_BOOK_TITLE: &str = "lorem";
+    const DEFAULT_BOOK_TEXT: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Cras ultrices imperdiet augue et facilisis. Duis dignissim libero eros, eu sagittis purus suscipit at. Vivamus sit amet bibendum ex. Ut convallis velit id odio tincidunt fringilla. Mauris interdum eleifend sapien, vitae luctus sem. Sed suscipit ultrices metus, ut iaculis urna sagittis vel. Ut elementum nisi ac diam mattis, non condimentum urna pretium. Proin hendrerit metus sed pretium lacinia. Praesent a purus rhoncus odio imperdiet blandit quis quis risus. Aliquam euismod, eros at congue laoreet, sem mi pellentesque augue, et dictum magna augue eget lectus. Nam ultrices justo justo, quis gravida justo semper eget. Pellentesque habitant morbi tristique senectus et netus et malesuada fames ac turpis egestas. Nunc hendrerit massa sed fringilla laoreet. In id quam tincidunt sem laoreet aliquet molestie a lacus. Donec felis dui, tempus tincidunt laoreet ut, convallis quis mauris.";
+    storage::import_book(DEFAULT_BOOK_TITLE, DEFAULT_BOOK_TEXT)
+        .map(|()| DEFAULT_BOOK_TITLE.to_string())
+        .map_err(|e| (DEFAULT_BOOK_TITLE.to_string(), e))
+}
+
+/// Opens a book, along with warnings about any tests which couldn't be read.
+fn open_text(title: &str) -> (OpenText, Vec<String>) {
+    let book_text = storage::load_book(title).unwrap_or_default();
+    let test_log = storage::load_tests(title);
+    let warnings = test_log
+        .errors
+        .iter()
+        .map(|error| format!("Skipped unreadable test in {}: {}", title, error))
+        .collect();
+
+    let book_title = title.to_string();
+    let save = move |record: &Record| storage::save_record(&book_title, record);
+
+    (OpenText::new(book_text, test_log.records, save), warnings)
 }
 
 impl Component for TermApp {
@@ -76,9 +371,9 @@ impl Component for TermApp {
 
         let panic_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic| {
-            _ = web_sys::window().unwrap().alert_with_message(
-                format!("Panicked, please reload. Error: {}", panic.to_string()).as_str(),
-            );
+            _ = web_sys::window()
+                .unwrap()
+                .alert_with_message(format!("Panicked, please reload. Error: {}", panic).as_str());
             panic_hook(panic);
         }));
 
@@ -87,49 +382,509 @@ impl Component for TermApp {
             .into_js_value()
             .into();
         window.set_onresize(Some(&func));
+        font::watch_pixel_ratio(ctx.link().callback(|()| TermAppMsg::Resized));
+        font::when_loaded(ctx.link().callback(|()| TermAppMsg::Resized));
 
-        let cb: Callback<KeyboardEvent> = ctx
-            .link()
-            .callback(|e: KeyboardEvent| TermAppMsg::KeyDown(to_key_down(e)));
+        let cb = ctx.link().callback(|()| TermAppMsg::Tick);
+        let func: Function = Closure::<dyn 'static + Fn()>::new(move || cb.emit(()))
+            .into_js_value()
+            .into();
+        let _ = window
+            .set_interval_with_callback_and_timeout_and_arguments_0(&func, TICK_RATE_MS as i32);
+
+        let cb: Callback<KeyboardEvent> = ctx.link().batch_callback(|e: KeyboardEvent| {
+            // Keys which the browser can't identify are typed into the hidden
+            // input, and are read from there instead.
+            if e.is_composing() || matches!(e.key().as_str(), "Unidentified" | "Process") {
+                return None;
+            }
+            let key_down = to_key_down(&e);
+            // Stop the hidden input receiving the key as well.
+            if matches!(key_down.code, K::Char(_)) && soft_keyboard::is_target(e.target()) {
+                e.prevent_default();
+            }
+            Some(TermAppMsg::KeyDown(key_down))
+        });
         let func: Function =
             Closure::<dyn 'static + Fn(KeyboardEvent)>::new(move |e: KeyboardEvent| cb.emit(e))
                 .into_js_value()
                 .into();
         window.set_onkeydown(Some(&func));
 
-        let book_text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Cras ultrices imperdiet augue et facilisis. Duis dignissim libero eros, eu sagittis purus suscipit at. Vivamus sit amet bibendum ex. Ut convallis velit id odio tincidunt fringilla. Mauris interdum eleifend sapien, vitae luctus sem. Sed suscipit ultrices metus, ut iaculis urna sagittis vel. Ut elementum nisi ac diam mattis, non condimentum urna pretium. Proin hendrerit metus sed pretium lacinia. Praesent a purus rhoncus odio imperdiet blandit quis quis risus. Aliquam euismod, eros at congue laoreet, sem mi pellentesque augue, et dictum magna augue eget lectus. Nam ultrices justo justo, quis gravida justo semper eget. Pellentesque habitant morbi tristique senectus et netus et malesuada fames ac turpis egestas. Nunc hendrerit massa sed fringilla laoreet. In id quam tincidunt sem laoreet aliquet molestie a lacus. Donec felis dui, tempus tincidunt laoreet ut, convallis quis mauris.";
-        let tests = Vec::new();
-
-        let save = move |tests: TestResult, _keypresses: Vec<KeyPress>| {
-            LocalStorage::set("lorem.test_result", &tests).expect("failed to set");
-            LocalStorage::set("lorem.keypresses", &tests).expect("failed to set");
+        storage::on_error(ctx.link().callback(TermAppMsg::StorageError));
+        storage::on_failed_save(ctx.link().callback(TermAppMsg::SaveFailed));
+        let books = storage::list_books();
+        let mut error = None;
+        let title = match storage::current_book()
+            .filter(|t| books.contains(t))
+            .or(books.first().cloned())
+        {
+            Some(title) => title,
+            None => add_default_book().unwrap_or_else(|(title, e)| {
+                error = Some(e);
+                title
+            }),
         };
 
-        let text = OpenText::new(book_text.into(), tests, save);
-        let app = App::new(());
+        let (text, warnings) = open_text(&title);
+        let mut app = App::new(Library);
+        app.settings = storage::load_settings();
+        app.error = error.map(|e| e.to_string()).or(warnings.into_iter().next());
+        app.book_title = title;
+        // New users choose what to start with.
+        if books.is_empty() && cfg!(feature = "samples") {
+            app.open_library();
+        }
+        TERMINAL
+            .term()
+            .backend_mut()
+            .set_background(app.settings.theme.background);
+
+        let soft_keyboard = soft_keyboard::create(ctx.link().callback(TermAppMsg::Text));
 
-        Self { app, text }
+        let font = storage::load_font();
+        font.apply();
+        TERMINAL.term().backend_mut().resize_buffer();
+
+        deep_link::watch(ctx.link().callback(|()| TermAppMsg::LinkChanged));
+        let mut term_app = Self {
+            app,
+            text,
+            font,
+            soft_keyboard,
+            touch: None,
+            wheel: 0.0,
+            linked_position: 0,
+            export_all: true,
+            failed_saves: Vec::new(),
+        };
+        term_app.linked_position = term_app.position();
+        term_app.follow_link();
+        term_app
     }
 
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             TermAppMsg::Resized => {
+                // Zooming changes the size of a cell as well as the window.
+                font::measure_cell();
                 TERMINAL.term().backend_mut().resize_buffer();
             }
             TermAppMsg::KeyDown(event) => {
+                let settings = self.app.settings.clone();
                 self.app.handle_key_events(
                     event,
                     &mut self.text,
-                    TERMINAL.term().size().unwrap().width - 2,
+                    TERMINAL.term().size().unwrap().width.saturating_sub(2),
                 );
+                if self.app.settings != settings {
+                    self.save_settings();
+                }
+                self.open_chosen();
+                self.update_link();
+            }
+            TermAppMsg::Text(text) => {
+                self.app.handle_text(&text, &mut self.text);
+                self.update_link();
+            }
+            TermAppMsg::Export(log, format) => {
+                let book = (!self.export_all).then_some(self.app.book_title.as_str());
+                export_history(log, format, book);
+                return false;
+            }
+            TermAppMsg::SetExportAll(all) => {
+                self.export_all = all;
+            }
+            TermAppMsg::ExportBundle => {
+                export_bundle(&self.app.settings);
+                return false;
+            }
+            TermAppMsg::ImportBundle(json) => {
+                if let Err(e) = self.import_bundle(&json) {
+                    alert(&format!("Failed to import bundle: {}", e));
+                }
+            }
+            TermAppMsg::SetTheme(name) => {
+                if let Some(theme) = Theme::builtin(&name) {
+                    self.set_theme(theme);
+                }
+            }
+            TermAppMsg::LoadTheme(toml) => match Theme::from_toml(&toml) {
+                Ok(theme) => self.set_theme(theme),
+                Err(e) => alert(&format!("Failed to load theme: {}", e)),
+            },
+            TermAppMsg::TouchStart(y) => {
+                self.touch = Some(Touch {
+                    last_y: y,
+                    scrolled: false,
+                });
+                return false;
+            }
+            TermAppMsg::TouchMove(y) => {
+                let Some(touch) = self.touch.as_mut() else {
+                    return false;
+                };
+                let (_, cell_height) = font::cell_size();
+                let rows = ((touch.last_y - y) / cell_height) as i32;
+                if rows == 0 {
+                    return false;
+                }
+                touch.last_y -= rows as f64 * cell_height;
+                touch.scrolled = true;
+                // Dragging upwards moves further into the book.
+                let code = if rows > 0 { K::Down } else { K::Up };
+                let width = TERMINAL.term().size().unwrap().width.saturating_sub(2);
+                for _ in 0..rows.unsigned_abs() {
+                    let key_down = KeyDown {
+                        code,
+                        mods: M::Unimplemented,
+                    };
+                    self.app.handle_key_events(key_down, &mut self.text, width);
+                }
+            }
+            TermAppMsg::TouchEnd => {
+                // A tap snaps back to the cursor, and brings up the keyboard.
+                if self.touch.take().is_some_and(|t| !t.scrolled) {
+                    let key_down = KeyDown {
+                        code: K::Esc,
+                        mods: M::Unimplemented,
+                    };
+                    let width = TERMINAL.term().size().unwrap().width.saturating_sub(2);
+                    self.app.handle_key_events(key_down, &mut self.text, width);
+                    if let Some(input) = &self.soft_keyboard {
+                        let _ = input.focus();
+                    }
+                } else {
+                    return false;
+                }
+            }
+            TermAppMsg::Wheel(delta) => {
+                let (_, cell_height) = font::cell_size();
+                self.wheel += delta;
+                let rows = (self.wheel / cell_height) as i32;
+                if rows == 0 {
+                    return false;
+                }
+                self.wheel -= rows as f64 * cell_height;
+                let event = if rows > 0 {
+                    handler::MouseEvent::ScrollDown
+                } else {
+                    handler::MouseEvent::ScrollUp
+                };
+                let screen = TERMINAL.term().size().unwrap();
+                for _ in 0..rows.unsigned_abs() {
+                    self.app.handle_mouse_events(event, &mut self.text, screen);
+                }
+            }
+            TermAppMsg::Click(column, row) => {
+                let screen = TERMINAL.term().size().unwrap();
+                let click = handler::MouseEvent::Click { column, row };
+                self.app.handle_mouse_events(click, &mut self.text, screen);
+                self.open_chosen();
+            }
+            TermAppMsg::SetFont(font) => {
+                font.apply();
+                TERMINAL.term().backend_mut().resize_buffer();
+                if let Err(e) = storage::save_font(&font) {
+                    self.app.error = Some(e.to_string());
+                }
+                self.font = font;
+            }
+            TermAppMsg::SetIdle(secs) => {
+                self.app.settings.idle_secs = secs;
+                self.save_settings();
+            }
+            TermAppMsg::StorageError(message) => {
+                self.app.error = Some(message);
+            }
+            TermAppMsg::SaveFailed(failed) => {
+                if failed.book_title == self.app.book_title {
+                    self.text.requeue(failed.record, &failed.error);
+                } else {
+                    self.app.error = Some(format!(
+                        "Failed to save progress in {}: {}",
+                        failed.book_title, failed.error
+                    ));
+                    self.failed_saves.push(failed);
+                }
+            }
+            TermAppMsg::LinkChanged => self.follow_link(),
+            TermAppMsg::OpenLibrary => self.app.open_library(),
+            TermAppMsg::Tick => {
+                self.retry_failed_saves();
+                self.app.tick(&mut self.text);
             }
         }
         true
     }
 
-    fn view(&self, _ctx: &Context<Self>) -> Html {
+    fn view(&self, ctx: &Context<Self>) -> Html {
         let mut term = TERMINAL.term();
         term.draw(|frame: &mut Frame<'_>| self.draw(frame)).unwrap();
-        term.backend_mut().render()
+        let export_button = |label: &str, log: Log, format: Format| {
+            html! {
+                <button
+                    onmousedown={|e: MouseEvent| e.prevent_default()}
+                    onclick={ctx.link().callback(move |_| TermAppMsg::Export(log, format))}
+                >
+                    { label }
+                </button>
+            }
+        };
+        let import_bundle = ctx.link().callback(TermAppMsg::ImportBundle);
+        let on_import = move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(file) = input.files().and_then(|f| f.get(0)) {
+                read_file(file, import_bundle.clone());
+            }
+            input.set_value("");
+        };
+        let load_theme = ctx.link().callback(TermAppMsg::LoadTheme);
+        let on_load_theme = move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(file) = input.files().and_then(|f| f.get(0)) {
+                read_file(file, load_theme.clone());
+            }
+            input.set_value("");
+        };
+        let set_theme = ctx.link().callback(TermAppMsg::SetTheme);
+        let on_theme = move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            set_theme.emit(select.value());
+            // Otherwise the arrow keys would keep changing the theme.
+            let _ = select.blur();
+        };
+        let current_theme = self.app.settings.theme.builtin_name();
+        let set_font = ctx.link().callback(TermAppMsg::SetFont);
+        let on_font_family = {
+            let font = self.font.clone();
+            let set_font = set_font.clone();
+            move |e: Event| {
+                let select: HtmlSelectElement = e.target_unchecked_into();
+                set_font.emit(Font {
+                    family: select.value(),
+                    ..font.clone()
+                });
+                let _ = select.blur();
+            }
+        };
+        let on_font_size = {
+            let font = self.font.clone();
+            move |e: Event| {
+                let select: HtmlSelectElement = e.target_unchecked_into();
+                if let Ok(size) = select.value().parse() {
+                    set_font.emit(Font {
+                        size,
+                        ..font.clone()
+                    });
+                }
+                let _ = select.blur();
+            }
+        };
+        let set_idle = ctx.link().callback(TermAppMsg::SetIdle);
+        let on_idle = move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            if let Ok(secs) = select.value().parse() {
+                set_idle.emit(secs);
+            }
+            let _ = select.blur();
+        };
+        let idle_secs = self.app.settings.idle_secs;
+        let set_export_all = ctx.link().callback(TermAppMsg::SetExportAll);
+        let on_export_scope = move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            set_export_all.emit(select.value() == "all");
+            let _ = select.blur();
+        };
+        html! {
+            <>
+                <div
+                    id="touch_area"
+                    ontouchstart={ctx.link().batch_callback(|e: TouchEvent| {
+                        e.touches().get(0).map(|t| TermAppMsg::TouchStart(t.client_y() as f64))
+                    })}
+                    ontouchmove={ctx.link().batch_callback(|e: TouchEvent| {
+                        e.touches().get(0).map(|t| TermAppMsg::TouchMove(t.client_y() as f64))
+                    })}
+                    ontouchend={ctx.link().callback(|_: TouchEvent| TermAppMsg::TouchEnd)}
+                    onwheel={ctx.link().callback(|e: WheelEvent| {
+                        let (_, cell_height) = font::cell_size();
+                        TermAppMsg::Wheel(match e.delta_mode() {
+                            WheelEvent::DOM_DELTA_PIXEL => e.delta_y(),
+                            WheelEvent::DOM_DELTA_LINE => e.delta_y() * cell_height,
+                            _ => e.delta_y().signum() * cell_height * 3.0,
+                        })
+                    })}
+                    onpointerdown={ctx.link().batch_callback(|e: PointerEvent| {
+                        // Taps are handled as touches.
+                        if e.pointer_type() != "mouse" || e.button() != 0 {
+                            return None;
+                        }
+                        font::cell_at(e.client_x() as f64, e.client_y() as f64)
+                            .map(|(column, row)| TermAppMsg::Click(column, row))
+                    })}
+                >
+                    { term.backend_mut().view() }
+                </div>
+                <div id="toolbar">
+                    <button
+                        onmousedown={|e: MouseEvent| e.prevent_default()}
+                        onclick={ctx.link().callback(|_| TermAppMsg::OpenLibrary)}
+                    >
+                        { "Library" }
+                    </button>
+                    <select onchange={on_export_scope}>
+                        <option value="all" selected={self.export_all}>{ "All books" }</option>
+                        <option value="open" selected={!self.export_all}>{ "This book" }</option>
+                    </select>
+                    { export_button("Export tests CSV", Log::Tests, Format::Csv) }
+                    { export_button("Export keypresses CSV", Log::KeyPresses, Format::Csv) }
+                    { export_button("Export tests JSON", Log::Tests, Format::Json) }
+                    { export_button("Export keypresses JSON", Log::KeyPresses, Format::Json) }
+                    <button
+                        onmousedown={|e: MouseEvent| e.prevent_default()}
+                        onclick={ctx.link().callback(|_| TermAppMsg::ExportBundle)}
+                    >
+                        { "Export bundle" }
+                    </button>
+                    <label onmousedown={|e: MouseEvent| e.prevent_default()}>
+                        { "Import bundle" }
+                        <input type="file" accept=".json" onchange={on_import} />
+                    </label>
+                    <select onchange={on_theme}>
+                        if current_theme.is_none() {
+                            <option selected=true disabled=true>{ "custom" }</option>
+                        }
+                        { for BUILTIN_THEMES.iter().map(|&name| html! {
+                            <option value={name} selected={current_theme == Some(name)}>{ name }</option>
+                        }) }
+                    </select>
+                    <select onchange={on_font_family}>
+                        { for FONT_FAMILIES.iter().map(|&family| html! {
+                            <option value={family} selected={self.font.family == family}>{ family }</option>
+                        }) }
+                    </select>
+                    <select onchange={on_font_size}>
+                        { for FONT_SIZES.iter().map(|&size| html! {
+                            <option value={size.to_string()} selected={self.font.size == size}>
+                                { format!("{}px", size) }
+                            </option>
+                        }) }
+                    </select>
+                    <select onchange={on_idle}>
+                        if !IDLE_SECS.contains(&idle_secs) {
+                            <option selected=true disabled=true>{ idle_label(idle_secs) }</option>
+                        }
+                        { for IDLE_SECS.iter().map(|&secs| html! {
+                            <option value={secs.to_string()} selected={idle_secs == secs}>
+                                { idle_label(secs) }
+                            </option>
+                        }) }
+                    </select>
+                    <label onmousedown={|e: MouseEvent| e.prevent_default()}>
+                        { "Load theme" }
+                        <input type="file" accept=".toml" onchange={on_load_theme} />
+                    </label>
+                </div>
+            </>
+        }
+    }
+}
+
+fn stored_books() -> Vec<(String, String, Vec<TestResult>, Vec<KeyPress>)> {
+    storage::list_books().into_iter().map(stored_book).collect()
+}
+
+fn stored_book(title: String) -> (String, String, Vec<TestResult>, Vec<KeyPress>) {
+    let text = storage::load_book(&title).unwrap_or_default();
+    let tests = storage::load_tests(&title).records;
+    let keypresses = storage::load_keypresses(&title).records;
+    (title, text, tests, keypresses)
+}
+
+/// Downloads the tests or keypresses of one book, or of every stored book.
+fn export_history(log: Log, format: Format, book: Option<&str>) {
+    let loaded = match book {
+        Some(title) => vec![stored_book(title.to_string())],
+        None => stored_books(),
+    };
+    let books = loaded
+        .iter()
+        .map(|(title, text, tests, keypresses)| History {
+            title,
+            text,
+            tests,
+            keypresses,
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = Vec::new();
+    let name = match log {
+        Log::Tests => {
+            export::write_tests(&books, format, &mut out).expect("failed to export tests");
+            "tests"
+        }
+        Log::KeyPresses => {
+            export::write_keypresses(&books, format, &mut out)
+                .expect("failed to export keypresses");
+            "keypresses"
+        }
+    };
+    download(
+        &format!("scrivenwright-{}.{}", name, format.extension()),
+        &String::from_utf8_lossy(&out),
+    );
+}
+
+fn export_bundle(settings: &Settings) {
+    let books = stored_books()
+        .into_iter()
+        .map(|(title, text, tests, keypresses)| BookBundle::new(title, text, tests, keypresses))
+        .collect();
+    let bundle = Bundle::new(Some(settings.clone()), books);
+    download(
+        "scrivenwright-bundle.json",
+        &bundle.to_json().expect("failed to export bundle"),
+    );
+}
+
+fn read_file(file: File, cb: Callback<String>) {
+    let reader = FileReader::new().unwrap();
+    let onload = {
+        let reader = reader.clone();
+        Closure::once_into_js(move || {
+            if let Some(contents) = reader.result().ok().and_then(|r| r.as_string()) {
+                cb.emit(contents);
+            }
+        })
+    };
+    reader.set_onload(Some(onload.unchecked_ref()));
+    reader.read_as_text(&file).unwrap();
+}
+
+fn confirm(message: &str) -> bool {
+    web_sys::window()
+        .and_then(|w| w.confirm_with_message(message).ok())
+        .unwrap_or(false)
+}
+
+fn alert(message: &str) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.alert_with_message(message);
     }
 }
+
+fn download(file_name: &str, contents: &str) {
+    let parts = Array::of1(&JsValue::from_str(contents));
+    let blob = Blob::new_with_str_sequence(&parts).unwrap();
+    let url = Url::create_object_url_with_blob(&blob).unwrap();
+    let anchor: HtmlAnchorElement = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.create_element("a").ok())
+        .and_then(|a| a.dyn_into().ok())
+        .unwrap();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    let _ = Url::revoke_object_url(&url);
+}
@@ -0,0 +1,101 @@
+//! Links into the library through the URL hash, such as
+//! `#book=moby-dick&chapter=3` or `#book=moby-dick&glyph=1200`. Books are
+//! named by their title, or by the title in lower case with words joined by
+//! dashes.
+
+use js_sys::{decode_uri_component, encode_uri_component};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use yew::Callback;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub book: String,
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    Glyph(usize),
+    Chapter(usize),
+}
+
+impl Link {
+    pub fn parse(hash: &str) -> Option<Self> {
+        let mut book = None;
+        let mut position = None;
+        for pair in hash.trim_start_matches('#').split('&') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let value = decode_uri_component(value)
+                .ok()
+                .and_then(|v| v.as_string())
+                .unwrap_or(value.to_string());
+            match key {
+                "book" => book = Some(value),
+                "glyph" => position = value.parse().ok().map(Position::Glyph),
+                "chapter" => position = value.parse().ok().map(Position::Chapter),
+                _ => {}
+            }
+        }
+        Some(Self {
+            book: book?,
+            position,
+        })
+    }
+
+    pub fn to_hash(&self) -> String {
+        let mut hash = format!("#book={}", encode_uri_component(&self.book));
+        match self.position {
+            Some(Position::Glyph(glyph)) => hash.push_str(&format!("&glyph={}", glyph)),
+            Some(Position::Chapter(chapter)) => hash.push_str(&format!("&chapter={}", chapter)),
+            None => {}
+        }
+        hash
+    }
+
+    /// Whether this link names the book with `title`.
+    pub fn names(&self, title: &str) -> bool {
+        self.book == title || self.book == slug(title)
+    }
+}
+
+fn slug(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The link in the page's URL, if it has one.
+pub fn current() -> Option<Link> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    Link::parse(&hash)
+}
+
+/// Adds `link` to the browser's history, unless it is already the current
+/// page. This doesn't cause a hash change event.
+pub fn push(link: &Link) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let hash = link.to_hash();
+    if window.location().hash().ok().as_deref() == Some(hash.as_str()) {
+        return;
+    }
+    if let Ok(history) = window.history() {
+        let _ = history.push_state_with_url(&JsValue::NULL, "", Some(&hash));
+    }
+}
+
+/// Calls `on_change` when the hash is changed, by following a link, editing
+/// the URL or going back through the history.
+pub fn watch(on_change: Callback<()>) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let handler = Closure::<dyn Fn()>::new(move || on_change.emit(()));
+    window.set_onhashchange(Some(handler.into_js_value().unchecked_ref()));
+}
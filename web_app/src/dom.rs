@@ -0,0 +1,78 @@
+use web_sys::{Document, Element};
+
+/// The terminal as a table of cells which are kept in the DOM between frames,
+/// so that drawing only touches the cells which changed.
+#[derive(Debug)]
+pub struct DomGrid {
+    document: Document,
+    table: Element,
+    cells: Vec<Vec<DomCell>>,
+}
+
+#[derive(Debug)]
+struct DomCell {
+    element: Element,
+    symbol: String,
+    css: String,
+}
+
+impl DomGrid {
+    /// Fails if there is no document to create elements in.
+    pub fn new() -> Option<Self> {
+        let document = web_sys::window()?.document()?;
+        let table = document.create_element("table").ok()?;
+        table.set_id("the_terminal");
+        Some(Self {
+            document,
+            table,
+            cells: Vec::new(),
+        })
+    }
+
+    pub fn element(&self) -> &Element {
+        &self.table
+    }
+
+    /// Rebuilds the table with blank cells.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.table.set_inner_html("");
+        self.cells.clear();
+        for _ in 0..height {
+            let Ok(tr) = self.document.create_element("tr") else {
+                return;
+            };
+            let mut row = Vec::with_capacity(width);
+            for _ in 0..width {
+                let Ok(element) = self.document.create_element("td") else {
+                    return;
+                };
+                let _ = tr.append_child(&element);
+                row.push(DomCell {
+                    element,
+                    symbol: String::new(),
+                    css: String::new(),
+                });
+            }
+            let _ = self.table.append_child(&tr);
+            self.cells.push(row);
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.cells.first().map_or(0, Vec::len), self.cells.len())
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, symbol: &str, css: String) {
+        let Some(cell) = self.cells.get_mut(y).and_then(|row| row.get_mut(x)) else {
+            return;
+        };
+        if cell.symbol != symbol {
+            cell.element.set_text_content(Some(symbol));
+            cell.symbol = symbol.to_string();
+        }
+        if cell.css != css {
+            let _ = cell.element.set_attribute("style", &css);
+            cell.css = css;
+        }
+    }
+}
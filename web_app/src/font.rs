@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use wasm_bindgen::{prelude::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Element, HtmlElement};
+use yew::Callback;
+
+pub const FONT_FAMILIES: &[&str] = &[
+    "Source Code Pro",
+    "Roboto Mono",
+    "Courier New",
+    "Menlo",
+    "Consolas",
+    "monospace",
+];
+pub const FONT_SIZES: &[u16] = &[12, 14, 16, 18, 20, 24, 28, 32, 40, 48, 64];
+
+thread_local! {
+    /// The size of a character cell in CSS pixels, as last measured.
+    static CELL_SIZE: Cell<(f64, f64)> = const { Cell::new((20.0, 44.0)) };
+}
+
+/// The font which the terminal is drawn in.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Font {
+    pub family: String,
+    /// The size in CSS pixels.
+    pub size: u16,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            family: FONT_FAMILIES[0].to_string(),
+            size: 32,
+        }
+    }
+}
+
+impl Font {
+    /// Draws the terminal in this font, and measures its cells.
+    pub fn apply(&self) {
+        let root = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.document_element())
+            .and_then(|e| e.dyn_into::<HtmlElement>().ok());
+        if let Some(root) = root {
+            let style = root.style();
+            let _ = style.set_property("--font-family", &format!("'{}', monospace", self.family));
+            let _ = style.set_property("--font-size", &format!("{}px", self.size));
+        }
+        measure_cell();
+    }
+}
+
+pub fn cell_size() -> (f64, f64) {
+    CELL_SIZE.with(Cell::get)
+}
+
+/// The column and row of the terminal's cell at a point in the window.
+pub fn cell_at(client_x: f64, client_y: f64) -> Option<(u16, u16)> {
+    let terminal = web_sys::window()?
+        .document()?
+        .get_element_by_id("the_terminal")?;
+    let rect = terminal.get_bounding_client_rect();
+    let (width, height) = cell_size();
+    let (x, y) = (client_x - rect.left(), client_y - rect.top());
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    Some(((x / width) as u16, (y / height) as u16))
+}
+
+/// Measures a cell of a hidden table which is styled like the terminal. This
+/// follows the font, and changes with the browser's zoom.
+pub fn measure_cell() {
+    let Some(probe) = probe() else {
+        return;
+    };
+    let rect = probe.get_bounding_client_rect();
+    if rect.width() > 0.0 && rect.height() > 0.0 {
+        CELL_SIZE.with(|c| c.set((rect.width(), rect.height())));
+    }
+}
+
+fn probe() -> Option<Element> {
+    let document = web_sys::window()?.document()?;
+    if let Some(cell) = document.get_element_by_id("cell_probe") {
+        return Some(cell);
+    }
+    let table = document.create_element("table").ok()?;
+    table.set_class_name("terminal");
+    let _ = table.set_attribute("style", "position: absolute; visibility: hidden;");
+    let row = document.create_element("tr").ok()?;
+    let cell = document.create_element("td").ok()?;
+    cell.set_id("cell_probe");
+    cell.set_text_content(Some("M"));
+    row.append_child(&cell).ok()?;
+    table.append_child(&row).ok()?;
+    document.body()?.append_child(&table).ok()?;
+    Some(cell)
+}
+
+/// Calls `on_change` whenever the device pixel ratio changes, such as when
+/// the window is moved to another screen. A media query only matches a single
+/// ratio, so a new one is made after each change.
+pub fn watch_pixel_ratio(on_change: Callback<()>) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let Ok(Some(list)) = window.match_media(&query) else {
+        return;
+    };
+    let handler = {
+        let list = list.clone();
+        Closure::once_into_js(move || {
+            list.set_onchange(None);
+            on_change.emit(());
+            watch_pixel_ratio(on_change);
+        })
+    };
+    list.set_onchange(Some(handler.unchecked_ref()));
+}
+
+/// Calls `on_load` once the fonts used by the page have loaded, as the cells
+/// are measured in a fallback font until the bundled one is ready.
+pub fn when_loaded(on_load: Callback<()>) {
+    let Some(fonts) = web_sys::window()
+        .and_then(|w| w.document())
+        .map(|d| d.fonts())
+    else {
+        return;
+    };
+    let Ok(ready) = fonts.ready() else {
+        return;
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        if JsFuture::from(ready).await.is_ok() {
+            on_load.emit(());
+        }
+    });
+}
@@ -0,0 +1,91 @@
+use crate::storage;
+use scrivenwright::app::{AppResult, PlatformAdapter, TextHandle};
+use scrivenwright::chapters;
+use scrivenwright::glyph_string::GlyphString;
+#[cfg(feature = "samples")]
+use scrivenwright::samples::SAMPLES;
+use scrivenwright::stats::Summary;
+
+/// Lists the stored books for the library screen, followed by the bundled
+/// samples which haven't been added yet.
+pub struct Library;
+
+impl PlatformAdapter for Library {
+    fn get_texts(&self) -> Vec<TextHandle> {
+        let books = storage::list_books();
+        let mut texts = books
+            .iter()
+            .map(|title| {
+                let text = storage::load_book(title).unwrap_or_default();
+                let summary = Summary::new(&text, &storage::load_tests(title).records);
+                TextHandle {
+                    name: title.clone(),
+                    author: None,
+                    length: summary.length,
+                    position: Some(summary.position),
+                    chapters: chapters::chapters(&GlyphString::new(text)).len(),
+                }
+            })
+            .collect::<Vec<_>>();
+        texts.extend(unadded_samples(&books));
+        texts
+    }
+}
+
+#[cfg(feature = "samples")]
+fn unadded_samples(books: &[String]) -> Vec<TextHandle> {
+    SAMPLES
+        .iter()
+        .filter(|s| !books.iter().any(|b| b == s.title))
+        .map(|s| {
+            let text = GlyphString::new(s.text());
+            TextHandle {
+                name: s.title.to_string(),
+                author: Some(s.author.to_string()),
+                length: text.len(),
+                position: None,
+                chapters: chapters::chapters(&text).len(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "samples"))]
+fn unadded_samples(_books: &[String]) -> Vec<TextHandle> {
+    Vec::new()
+}
+
+/// The titles of the stored books and the bundled samples.
+pub fn titles() -> Vec<String> {
+    let mut titles = storage::list_books();
+    for title in sample_titles() {
+        if !titles.iter().any(|t| t == title) {
+            titles.push(title.to_string());
+        }
+    }
+    titles
+}
+
+#[cfg(feature = "samples")]
+fn sample_titles() -> Vec<&'static str> {
+    SAMPLES.iter().map(|s| s.title).collect()
+}
+
+#[cfg(not(feature = "samples"))]
+fn sample_titles() -> Vec<&'static str> {
+    Vec::new()
+}
+
+/// Adds a bundled sample to the library the first time it is used. Returns
+/// whether `book_title` is now in the library.
+pub fn add_sample(book_title: &str) -> AppResult<bool> {
+    if storage::list_books().iter().any(|b| b == book_title) {
+        return Ok(true);
+    }
+    #[cfg(feature = "samples")]
+    if let Some(sample) = scrivenwright::samples::find(book_title) {
+        storage::import_book(book_title, &sample.text())?;
+        return Ok(true);
+    }
+    Ok(false)
+}
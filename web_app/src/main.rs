@@ -24,6 +24,12 @@ use send_wrapper::SendWrapper;
 use terminal::WebTerm;
 
 pub mod app;
+pub mod deep_link;
+pub mod dom;
+pub mod font;
+pub mod library;
+pub mod soft_keyboard;
+pub mod storage;
 pub mod terminal;
 
 pub static TERMINAL: Renderer = Renderer::new();
@@ -48,6 +54,12 @@ impl Renderer {
     }
 }
 
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 struct TermDeref<'a>(MutexGuard<'a, SendWrapper<Terminal<WebTerm>>>);
 
 impl<'a> Deref for TermDeref<'a> {
@@ -64,7 +76,20 @@ impl<'a> DerefMut for TermDeref<'a> {
     }
 }
 
+/// Registers the service worker which caches the app for offline use. It is
+/// copied next to index.html by Trunk.
+fn register_service_worker() {
+    if let Some(window) = web_sys::window() {
+        let container = window.navigator().service_worker();
+        let _ = container.register("sw.js");
+    }
+}
+
 fn main() {
-    TERMINAL.load();
-    yew::Renderer::<TermApp>::new().render();
+    register_service_worker();
+    wasm_bindgen_futures::spawn_local(async {
+        storage::load().await;
+        TERMINAL.load();
+        yew::Renderer::<TermApp>::new().render();
+    });
 }
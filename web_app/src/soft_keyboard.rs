@@ -0,0 +1,95 @@
+use wasm_bindgen::{prelude::Closure, JsCast};
+use web_sys::{CompositionEvent, EventTarget, HtmlInputElement, InputEvent};
+use yew::Callback;
+
+const ID: &str = "soft_keyboard";
+
+/// A hidden text input which brings up the on-screen keyboard when focused.
+/// On-screen keyboards often don't report which key was pressed in their
+/// keydown events, so what they type is read from the input instead. It is
+/// also where input methods and dead keys compose text on the desktop, so it
+/// is focused from the start.
+pub fn create(on_text: Callback<String>) -> Option<HtmlInputElement> {
+    let document = web_sys::window()?.document()?;
+    let input: HtmlInputElement = document.create_element("input").ok()?.dyn_into().ok()?;
+    input.set_id(ID);
+    for (name, value) in [
+        ("autocapitalize", "off"),
+        ("autocomplete", "off"),
+        ("autocorrect", "off"),
+        ("spellcheck", "false"),
+        ("aria-label", "Type here"),
+    ] {
+        let _ = input.set_attribute(name, value);
+    }
+
+    // Most typing can be read and cancelled before it reaches the input.
+    let before_input = {
+        let on_text = on_text.clone();
+        Closure::<dyn Fn(InputEvent)>::new(move |e: InputEvent| {
+            if e.is_composing() {
+                return;
+            }
+            let text = match e.input_type().as_str() {
+                "insertText" | "insertReplacementText" => e.data().unwrap_or_default(),
+                "insertLineBreak" | "insertParagraph" => "\n".to_string(),
+                _ => String::new(),
+            };
+            if e.cancelable() {
+                e.prevent_default();
+            }
+            if !text.is_empty() {
+                on_text.emit(text);
+            }
+        })
+    };
+    let _ = input.add_event_listener_with_callback(
+        "beforeinput",
+        before_input.into_js_value().unchecked_ref(),
+    );
+
+    // Composed text can't be cancelled, so it is taken from the input once
+    // it has been committed.
+    let composition_end = {
+        let input = input.clone();
+        let on_text = on_text.clone();
+        Closure::<dyn Fn(CompositionEvent)>::new(move |e: CompositionEvent| {
+            input.set_value("");
+            if let Some(text) = e.data().filter(|t| !t.is_empty()) {
+                on_text.emit(text);
+            }
+        })
+    };
+    let _ = input.add_event_listener_with_callback(
+        "compositionend",
+        composition_end.into_js_value().unchecked_ref(),
+    );
+
+    // Anything else which couldn't be cancelled ends up in the input's value.
+    let after_input = {
+        let input = input.clone();
+        Closure::<dyn Fn(InputEvent)>::new(move |e: InputEvent| {
+            if e.is_composing() {
+                return;
+            }
+            let text = input.value();
+            input.set_value("");
+            if !text.is_empty() {
+                on_text.emit(text);
+            }
+        })
+    };
+    let _ = input
+        .add_event_listener_with_callback("input", after_input.into_js_value().unchecked_ref());
+
+    document.body()?.append_child(&input).ok()?;
+    let _ = input.focus();
+    Some(input)
+}
+
+/// Whether an event was sent to the hidden input.
+pub fn is_target(target: Option<EventTarget>) -> bool {
+    target
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .is_some_and(|i| i.id() == ID)
+}
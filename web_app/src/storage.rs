@@ -0,0 +1,443 @@
+//! Books and their history are kept in IndexedDB, which holds far more than
+//! local storage and is available offline. IndexedDB can only be used
+//! asynchronously, so everything is read into memory by [`load`] before the
+//! app starts, and writes are made to memory straight away then saved in the
+//! background. Small values, such as settings, stay in local storage.
+//!
+//! Records added to a log are stored under keys of their own, `[log key,
+//! chunk number]`, so that saving doesn't rewrite the whole log. They are
+//! merged back into the log the next time the library is loaded.
+
+use crate::font::Font;
+use gloo_storage::{LocalStorage, Storage};
+use idb::{Database, DatabaseEvent, Factory, KeyRange, ObjectStoreParams, TransactionMode};
+use js_sys::Array;
+use scrivenwright::app::AppResult;
+use scrivenwright::bundle::Timestamped;
+use scrivenwright::schema::{self, DecodedLog, Versioned};
+use scrivenwright::settings::Settings;
+use scrivenwright::text::{KeyPress, Record, TestResult};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use yew::Callback;
+
+const BOOKS_KEY: &str = "books";
+const CURRENT_BOOK_KEY: &str = "current_book";
+const SETTINGS_KEY: &str = "settings";
+const FONT_KEY: &str = "font";
+
+const DATABASE_NAME: &str = "scrivenwright";
+const DATABASE_VERSION: u32 = 1;
+/// Holds string values under the same keys they had in local storage.
+const RECORDS_STORE: &str = "records";
+
+thread_local! {
+    static RECORDS: RefCell<HashMap<String, String>> = RefCell::default();
+    /// Chunks added to each log which haven't been merged into it yet, by
+    /// chunk number.
+    static APPENDED: RefCell<HashMap<String, BTreeMap<u32, String>>> = RefCell::default();
+    /// The number of the last chunk added to each log. Numbers aren't reused.
+    static LAST_CHUNK: RefCell<HashMap<String, u32>> = RefCell::default();
+    /// None if IndexedDB couldn't be opened, in which case local storage is
+    /// used as before.
+    static DATABASE: RefCell<Option<Rc<Database>>> = RefCell::default();
+    static ON_ERROR: RefCell<Option<Callback<String>>> = RefCell::default();
+    static ON_FAILED_SAVE: RefCell<Option<Callback<FailedSave>>> = RefCell::default();
+}
+
+/// A record which couldn't be saved in the background, to be saved again.
+#[derive(Debug)]
+pub struct FailedSave {
+    pub book_title: String,
+    pub record: Record,
+    pub error: String,
+}
+
+/// Reads the library into memory, moving it out of local storage the first
+/// time IndexedDB is used.
+pub async fn load() {
+    let records = match open_database().await {
+        Ok(database) => {
+            let records = match read_all(&database).await {
+                Ok((records, appended)) if records.is_empty() && appended.is_empty() => {
+                    migrate_local_storage(&database).await
+                }
+                Ok((records, appended)) => Ok(merge_appended(&database, records, appended).await),
+                Err(e) => Err(e),
+            };
+            DATABASE.with(|d| *d.borrow_mut() = Some(Rc::new(database)));
+            records
+        }
+        Err(e) => Err(e),
+    };
+    let records = records.unwrap_or_else(|e| {
+        DATABASE.with(|d| *d.borrow_mut() = None);
+        report_error(format!(
+            "Couldn't open the library, using local storage: {}",
+            e
+        ));
+        read_local_storage()
+    });
+    RECORDS.with(|r| *r.borrow_mut() = records);
+}
+
+/// Sets where errors from saving in the background are sent.
+pub fn on_error(callback: Callback<String>) {
+    ON_ERROR.with(|e| *e.borrow_mut() = Some(callback));
+}
+
+/// Sets where records which couldn't be saved in the background are sent.
+pub fn on_failed_save(callback: Callback<FailedSave>) {
+    ON_FAILED_SAVE.with(|f| *f.borrow_mut() = Some(callback));
+}
+
+fn report_error(message: String) {
+    match ON_ERROR.with(|e| e.borrow().clone()) {
+        Some(callback) => callback.emit(message),
+        None => web_sys::console::error_1(&message.into()),
+    }
+}
+
+async fn open_database() -> Result<Database, idb::Error> {
+    let mut request = Factory::new()?.open(DATABASE_NAME, Some(DATABASE_VERSION))?;
+    request.on_upgrade_needed(|event| {
+        if let Ok(database) = event.database() {
+            let _ = database.create_object_store(RECORDS_STORE, ObjectStoreParams::new());
+        }
+    });
+    request.await
+}
+
+type Chunks = HashMap<String, BTreeMap<u32, String>>;
+
+/// Reads every value, along with the chunks appended to each log.
+async fn read_all(database: &Database) -> Result<(HashMap<String, String>, Chunks), idb::Error> {
+    let transaction = database.transaction(&[RECORDS_STORE], TransactionMode::ReadOnly)?;
+    let store = transaction.object_store(RECORDS_STORE)?;
+    let keys = store.get_all_keys(None, None)?.await?;
+    let values = store.get_all(None, None)?.await?;
+    transaction.await?;
+    let (mut records, mut appended) = (HashMap::new(), Chunks::new());
+    for (key, value) in keys.into_iter().zip(values) {
+        let Some(value) = value.as_string() else {
+            continue;
+        };
+        if let Some(key) = key.as_string() {
+            records.insert(key, value);
+        } else if let Some((key, chunk)) = parse_chunk_key(&key) {
+            appended.entry(key).or_default().insert(chunk, value);
+        }
+    }
+    Ok((records, appended))
+}
+
+/// Merges the chunks appended to each log into it. If the merged logs can't
+/// be saved, the chunks are kept as they are.
+async fn merge_appended(
+    database: &Database,
+    mut records: HashMap<String, String>,
+    appended: Chunks,
+) -> HashMap<String, String> {
+    let mut merged = HashMap::new();
+    for (key, chunks) in &appended {
+        let mut log = records.get(key).cloned().unwrap_or_default();
+        log.extend(chunks.values().map(String::as_str));
+        merged.insert(key.clone(), log);
+    }
+    match replace_logs(database, &merged).await {
+        Ok(()) => records.extend(merged),
+        Err(e) => {
+            report_error(format!("Couldn't tidy up the library: {}", e));
+            LAST_CHUNK.with(|n| {
+                n.borrow_mut().extend(
+                    appended.iter().filter_map(|(key, chunks)| {
+                        Some((key.clone(), *chunks.last_key_value()?.0))
+                    }),
+                )
+            });
+            APPENDED.with(|a| *a.borrow_mut() = appended);
+        }
+    }
+    records
+}
+
+/// Copies the library from local storage, then removes it from there once
+/// it has been saved.
+async fn migrate_local_storage(database: &Database) -> Result<HashMap<String, String>, idb::Error> {
+    let records = read_local_storage();
+    if records.is_empty() {
+        return Ok(records);
+    }
+    let transaction = database.transaction(&[RECORDS_STORE], TransactionMode::ReadWrite)?;
+    let store = transaction.object_store(RECORDS_STORE)?;
+    for (key, value) in &records {
+        store
+            .put(&JsValue::from_str(value), Some(&JsValue::from_str(key)))?
+            .await?;
+    }
+    transaction.commit()?.await?;
+    for key in records.keys() {
+        LocalStorage::delete(key);
+    }
+    Ok(records)
+}
+
+fn read_local_storage() -> HashMap<String, String> {
+    let books: Vec<String> = LocalStorage::get(BOOKS_KEY).unwrap_or_default();
+    let mut records = HashMap::new();
+    if let Ok(Some(list)) = LocalStorage::raw().get_item(BOOKS_KEY) {
+        records.insert(BOOKS_KEY.to_string(), list);
+    }
+    for book in &books {
+        for key in [text_key(book), test_key(book), keypress_key(book)] {
+            if let Ok(Some(value)) = LocalStorage::raw().get_item(&key) {
+                records.insert(key, value);
+            }
+        }
+    }
+    records
+}
+
+async fn put(database: &Database, key: &JsValue, value: &str) -> Result<(), idb::Error> {
+    let transaction = database.transaction(&[RECORDS_STORE], TransactionMode::ReadWrite)?;
+    let store = transaction.object_store(RECORDS_STORE)?;
+    store.put(&JsValue::from_str(value), Some(key))?.await?;
+    transaction.commit()?.await?;
+    Ok(())
+}
+
+/// Replaces logs along with the chunks appended to them, all at once.
+async fn replace_logs(
+    database: &Database,
+    logs: &HashMap<String, String>,
+) -> Result<(), idb::Error> {
+    let transaction = database.transaction(&[RECORDS_STORE], TransactionMode::ReadWrite)?;
+    let store = transaction.object_store(RECORDS_STORE)?;
+    for (key, value) in logs {
+        store
+            .put(&JsValue::from_str(value), Some(&JsValue::from_str(key)))?
+            .await?;
+        let chunks = KeyRange::bound(&chunk_key(key, 0), &chunk_key(key, u32::MAX), None, None)?;
+        store.delete(chunks)?.await?;
+    }
+    transaction.commit()?.await?;
+    Ok(())
+}
+
+fn chunk_key(key: &str, chunk: u32) -> JsValue {
+    Array::of2(&JsValue::from_str(key), &JsValue::from(chunk)).into()
+}
+
+fn parse_chunk_key(key: &JsValue) -> Option<(String, u32)> {
+    let key = key.dyn_ref::<Array>()?;
+    Some((key.get(0).as_string()?, key.get(1).as_f64()? as u32))
+}
+
+fn text_key(book_title: &str) -> String {
+    format!("{}.text", book_title)
+}
+
+fn test_key(book_title: &str) -> String {
+    format!("{}.tests", book_title)
+}
+
+fn keypress_key(book_title: &str) -> String {
+    format!("{}.keypresses", book_title)
+}
+
+pub fn list_books() -> Vec<String> {
+    get_record(BOOKS_KEY)
+        .and_then(|books| serde_json::from_str(&books).ok())
+        .unwrap_or_default()
+}
+
+pub fn load_book(book_title: &str) -> Option<String> {
+    get_record(&text_key(book_title))
+}
+
+/// Adds a book, which has already been normalized, to the library.
+pub fn import_book(book_title: &str, text: &str) -> AppResult<()> {
+    let mut books = list_books();
+    if !books.iter().any(|b| b == book_title) {
+        books.push(book_title.to_string());
+        set_record(BOOKS_KEY, serde_json::to_string(&books)?)?;
+    }
+    set_record(&text_key(book_title), text.to_string())
+}
+
+pub fn current_book() -> Option<String> {
+    LocalStorage::get(CURRENT_BOOK_KEY).ok()
+}
+
+pub fn set_current_book(book_title: &str) -> AppResult<()> {
+    Ok(LocalStorage::set(CURRENT_BOOK_KEY, book_title)?)
+}
+
+pub fn load_settings() -> Settings {
+    LocalStorage::get(SETTINGS_KEY).unwrap_or_default()
+}
+
+pub fn save_settings(settings: &Settings) -> AppResult<()> {
+    Ok(LocalStorage::set(SETTINGS_KEY, settings)?)
+}
+
+pub fn load_font() -> Font {
+    LocalStorage::get(FONT_KEY).unwrap_or_default()
+}
+
+pub fn save_font(font: &Font) -> AppResult<()> {
+    Ok(LocalStorage::set(FONT_KEY, font)?)
+}
+
+pub fn load_tests(book_title: &str) -> DecodedLog<TestResult> {
+    load_log(&test_key(book_title))
+}
+
+pub fn load_keypresses(book_title: &str) -> DecodedLog<KeyPress> {
+    load_log(&keypress_key(book_title))
+}
+
+/// Saves a test, or the keypresses made during one. If saving it fails in
+/// the background, it is sent to [`on_failed_save`].
+pub fn save_record(book_title: &str, record: &Record) -> AppResult<()> {
+    let (key, contents) = match record {
+        Record::Test(test) => (
+            test_key(book_title),
+            encode_log(std::slice::from_ref(test))?,
+        ),
+        Record::KeyPresses(keypresses) => (keypress_key(book_title), encode_log(keypresses)?),
+    };
+    let failed = FailedSave {
+        book_title: book_title.to_string(),
+        record: record.clone(),
+        error: String::new(),
+    };
+    append_log(&key, contents, failed)
+}
+
+pub fn replace_tests(book_title: &str, tests: &[TestResult]) -> AppResult<()> {
+    replace_log(&test_key(book_title), tests)
+}
+
+pub fn replace_keypresses(book_title: &str, keypresses: &[KeyPress]) -> AppResult<()> {
+    replace_log(&keypress_key(book_title), keypresses)
+}
+
+/// Decodes a log, in the order its records were made, as a record which
+/// failed to save is appended again after later ones.
+fn load_log<T: Versioned + Timestamped>(key: &str) -> DecodedLog<T> {
+    let mut contents = get_record(key).unwrap_or_default();
+    APPENDED.with(|a| {
+        if let Some(chunks) = a.borrow().get(key) {
+            contents.extend(chunks.values().map(String::as_str));
+        }
+    });
+    let mut log = schema::decode_log::<T>(&contents);
+    log.records.sort_by_key(T::timestamp);
+    log
+}
+
+/// Adds records to a log, as a chunk of their own unless IndexedDB isn't
+/// available.
+fn append_log(key: &str, contents: String, mut failed: FailedSave) -> AppResult<()> {
+    let Some(database) = DATABASE.with(|d| d.borrow().clone()) else {
+        let mut log = get_record(key).unwrap_or_default();
+        log.push_str(&contents);
+        return set_record(key, log);
+    };
+    let chunk = LAST_CHUNK.with(|n| {
+        let mut last = n.borrow_mut();
+        let last = last.entry(key.to_string()).or_insert(0);
+        *last += 1;
+        *last
+    });
+    APPENDED.with(|a| {
+        let mut appended = a.borrow_mut();
+        appended
+            .entry(key.to_string())
+            .or_default()
+            .insert(chunk, contents.clone());
+    });
+    let key = key.to_string();
+    wasm_bindgen_futures::spawn_local(async move {
+        let Err(e) = put(&database, &chunk_key(&key, chunk), &contents).await else {
+            return;
+        };
+        // Unless the log has been replaced since, which saved the record.
+        let removed = APPENDED.with(|a| {
+            let mut appended = a.borrow_mut();
+            appended.get_mut(&key).and_then(|c| c.remove(&chunk))
+        });
+        if removed.is_none() {
+            return;
+        }
+        failed.error = e.to_string();
+        match ON_FAILED_SAVE.with(|f| f.borrow().clone()) {
+            Some(callback) => callback.emit(failed),
+            None => report_error(format!("Failed to save progress: {}", failed.error)),
+        }
+    });
+    Ok(())
+}
+
+/// Replaces the records in a log. Lines which couldn't be decoded are kept, as
+/// they can't have been merged.
+fn replace_log<T: Versioned + Timestamped>(key: &str, records: &[T]) -> AppResult<()> {
+    let mut contents = encode_log(records)?;
+    for error in load_log::<T>(key).errors {
+        contents.push_str(&error.content);
+        contents.push('\n');
+    }
+    let Some(database) = DATABASE.with(|d| d.borrow().clone()) else {
+        return set_record(key, contents);
+    };
+    let logs = HashMap::from([(key.to_string(), contents.clone())]);
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = replace_logs(&database, &logs).await {
+            report_error(format!("Failed to save progress: {}", e));
+        }
+    });
+    APPENDED.with(|a| a.borrow_mut().remove(key));
+    RECORDS.with(|r| r.borrow_mut().insert(key.to_string(), contents));
+    Ok(())
+}
+
+fn get_record(key: &str) -> Option<String> {
+    RECORDS.with(|r| r.borrow().get(key).cloned())
+}
+
+/// Keeps a value in memory and saves it, in the background unless IndexedDB
+/// isn't available.
+fn set_record(key: &str, value: String) -> AppResult<()> {
+    match DATABASE.with(|d| d.borrow().clone()) {
+        Some(database) => {
+            let (key_owned, value_owned) = (key.to_string(), value.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = put(&database, &JsValue::from_str(&key_owned), &value_owned).await {
+                    report_error(format!("Failed to save progress: {}", e));
+                }
+            });
+        }
+        None => set_item(key, &value)?,
+    }
+    RECORDS.with(|r| r.borrow_mut().insert(key.to_string(), value));
+    Ok(())
+}
+
+/// Writes a raw value, which fails when the browser's storage quota is used up.
+fn set_item(key: &str, value: &str) -> AppResult<()> {
+    LocalStorage::raw()
+        .set_item(key, value)
+        .map_err(|e| format!("Couldn't save to local storage: {:?}", e).into())
+}
+
+fn encode_log<T: Versioned>(records: &[T]) -> AppResult<String> {
+    let mut contents = String::new();
+    for record in records {
+        contents.push_str(&schema::encode(record)?);
+        contents.push('\n');
+    }
+    Ok(contents)
+}
@@ -1,24 +1,80 @@
+use crate::dom::DomGrid;
+use crate::font;
 use ratatui::{
     buffer::Cell,
     prelude::{Backend, Rect},
     style::{Color, Modifier},
 };
 use std::{borrow::Cow, io::Result};
-use wasm_bindgen::JsValue;
 use yew::{html, Html};
 
+/// Drawn for cells with no background colour, and around the terminal.
+const DEFAULT_BACKGROUND: &str = "darkslategrey";
+const DEFAULT_FOREGROUND: &str = "white";
+
+/// Draws the terminal into a table. By default the table is kept in the DOM
+/// and only changed cells are updated. Adding `?renderer=table` to the URL
+/// falls back to rebuilding the whole table on every frame, which is also
+/// used if the DOM can't be accessed directly.
 #[derive(Debug, Default)]
 pub struct WebTerm {
     buffer: Vec<Vec<Cell>>,
     rendered: Html,
+    background: Cow<'static, str>,
+    grid: Option<DomGrid>,
 }
 
 impl WebTerm {
     pub fn new() -> Self {
-        Self {
+        let table_requested = web_sys::window()
+            .and_then(|w| w.location().search().ok())
+            .is_some_and(|s| s.contains("renderer=table"));
+        let mut term = Self {
             buffer: Self::get_sized_buffer(),
             rendered: Html::default(),
+            background: DEFAULT_BACKGROUND.into(),
+            grid: if table_requested {
+                None
+            } else {
+                DomGrid::new()
+            },
+        };
+        term.repaint();
+        term
+    }
+
+    /// Redraws every cell of the kept table, for when it has been resized or
+    /// the default colours have changed.
+    fn repaint(&mut self) {
+        let Some(mut grid) = self.grid.take() else {
+            return;
+        };
+        let (width, height) = (self.buffer.first().map_or(0, Vec::len), self.buffer.len());
+        if grid.size() != (width, height) {
+            grid.resize(width, height);
         }
+        for (y, line) in self.buffer.iter().enumerate() {
+            for (x, cell) in line.iter().enumerate() {
+                grid.set(x, y, cell.symbol(), self.cell_css(cell));
+            }
+        }
+        self.rendered = Html::VRef(grid.element().clone().into());
+        self.grid = Some(grid);
+    }
+
+    /// Sets the colour drawn for cells with no background colour, and around
+    /// the terminal. `Color::Reset` restores the default.
+    pub fn set_background(&mut self, color: Color) {
+        self.background = to_css_color(color).unwrap_or(DEFAULT_BACKGROUND.into());
+        if let Some(body) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.body())
+        {
+            let _ = body
+                .style()
+                .set_property("background-color", &self.background);
+        }
+        self.repaint();
     }
 
     fn get_sized_buffer() -> Vec<Vec<Cell>> {
@@ -30,19 +86,16 @@ impl WebTerm {
         self.rendered.clone()
     }
 
+    /// Rebuilds the whole table, when the cells aren't kept in the DOM.
     pub fn render(&mut self) -> Html {
+        if self.grid.is_some() {
+            return self.rendered.clone();
+        }
         let mut rows: Vec<Html> = Vec::with_capacity(self.buffer.len());
-        for line in self.buffer.clone() {
+        for line in &self.buffer {
             let mut row: Vec<Html> = Vec::with_capacity(line.len());
             for cell in line {
-                let Cell {
-                    fg, bg, modifier, ..
-                } = cell;
-                let fg = to_css_color(fg).unwrap_or("white".into());
-                let bg = to_css_color(bg).unwrap_or("darkslategrey".into());
-                let mut style =
-                    format!("color: {fg}; background-color: {bg}; white-space: pre-line;");
-                extend_css(modifier, &mut style);
+                let style = self.cell_css(cell);
                 row.push(html! { <td style={ style }> { cell.symbol().to_owned() } </td> });
             }
             rows.push(html! { <tr> { for row.into_iter() } </tr> });
@@ -52,10 +105,28 @@ impl WebTerm {
         self.rendered.clone()
     }
 
+    fn cell_css(&self, cell: &Cell) -> String {
+        let mods = cell.modifier;
+        let mut fg = to_css_color(cell.fg).unwrap_or(DEFAULT_FOREGROUND.into());
+        let mut bg = to_css_color(cell.bg).unwrap_or(self.background.clone());
+        if mods.contains(Modifier::REVERSED) {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if mods.contains(Modifier::HIDDEN) {
+            fg = bg.clone();
+        } else if mods.contains(Modifier::DIM) {
+            fg = format!("color-mix(in srgb, {fg} 60%, {bg})").into();
+        }
+        let mut css = format!("color: {fg}; background-color: {bg}; white-space: pre-line;");
+        extend_css(mods, &mut css);
+        css
+    }
+
     pub fn resize_buffer(&mut self) {
         let (width, height) = get_window_size();
         if self.buffer.len() != height as usize || self.buffer[0].len() != width as usize {
             self.buffer = Self::get_sized_buffer();
+            self.repaint();
         }
     }
 }
@@ -68,9 +139,19 @@ impl Backend for WebTerm {
         for (x, y, cell) in content {
             let y = y as usize;
             let x = x as usize;
-            let line = &mut self.buffer[y];
-            line.extend(std::iter::repeat_with(Cell::default).take(x.saturating_sub(line.len())));
+            let Some(line) = self.buffer.get_mut(y) else {
+                continue;
+            };
+            line.extend(
+                std::iter::repeat_with(Cell::default).take((x + 1).saturating_sub(line.len())),
+            );
             line[x] = cell.clone();
+            if self.grid.is_some() {
+                let css = self.cell_css(cell);
+                if let Some(grid) = self.grid.as_mut() {
+                    grid.set(x, y, cell.symbol(), css);
+                }
+            }
         }
         Ok(())
     }
@@ -93,6 +174,7 @@ impl Backend for WebTerm {
 
     fn clear(&mut self) -> Result<()> {
         self.buffer = Self::get_sized_buffer();
+        self.repaint();
         Ok(())
     }
 
@@ -111,24 +193,22 @@ impl Backend for WebTerm {
     }
 }
 
+/// The number of character cells which fit in the window.
 pub fn get_window_size() -> (u16, u16) {
     let (w, h) = get_raw_window_size();
-    ((w / 20), (h / 44))
+    let (cell_width, cell_height) = font::cell_size();
+    ((w / cell_width) as u16, (h / cell_height) as u16)
 }
 
-fn get_raw_window_size() -> (u16, u16) {
-    fn js_val_to_int<I: TryFrom<usize>>(val: JsValue) -> Option<I> {
-        val.as_f64().and_then(|i| I::try_from(i as usize).ok())
-    }
-
+fn get_raw_window_size() -> (f64, f64) {
     web_sys::window()
         .and_then(|s| {
             s.inner_width()
                 .ok()
-                .and_then(js_val_to_int::<u16>)
-                .zip(s.inner_height().ok().and_then(js_val_to_int::<u16>))
+                .and_then(|w| w.as_f64())
+                .zip(s.inner_height().ok().and_then(|h| h.as_f64()))
         })
-        .unwrap_or((120, 120))
+        .unwrap_or((2400.0, 5280.0))
 }
 
 fn to_css_color(c: Color) -> Option<Cow<'static, str>> {
@@ -150,8 +230,47 @@ fn to_css_color(c: Color) -> Option<Cow<'static, str>> {
         Color::LightMagenta => Some("#ff00ff".into()),
         Color::LightCyan => Some("lightcyan".into()),
         Color::White => Some("white".into()),
-        Color::Rgb(r, g, b) => Some(format!("#{r:X}{g:X}{b:X}").into()),
-        Color::Indexed(_) => unimplemented!(),
+        Color::Rgb(r, g, b) => Some(format!("#{r:02X}{g:02X}{b:02X}").into()),
+        Color::Indexed(i) => to_css_color(indexed_color(i)),
+    }
+}
+
+/// Maps the xterm 256 colour palette to the colours above. The first sixteen
+/// are the named colours, in the order terminals number them.
+fn indexed_color(i: u8) -> Color {
+    const NAMED: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match i {
+        0..=15 => NAMED[i as usize],
+        16..=231 => {
+            let i = i - 16;
+            Color::Rgb(
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        232..=255 => {
+            let level = 8 + (i - 232) * 10;
+            Color::Rgb(level, level, level)
+        }
     }
 }
 
@@ -162,7 +281,21 @@ fn extend_css(mods: Modifier, css: &mut String) {
     if mods.contains(Modifier::ITALIC) {
         css.push_str(" font-style: oblique;");
     }
-    if mods.contains(Modifier::UNDERLINED) {
-        css.push_str(" text-decoration: underline;");
+    let decorations = [
+        (Modifier::UNDERLINED, "underline"),
+        (Modifier::CROSSED_OUT, "line-through"),
+    ]
+    .into_iter()
+    .filter(|&(m, _)| mods.contains(m))
+    .map(|(_, d)| d)
+    .collect::<Vec<_>>();
+    if !decorations.is_empty() {
+        css.push_str(&format!(" text-decoration: {};", decorations.join(" ")));
+    }
+    // The keyframes are defined in index.html.
+    if mods.contains(Modifier::RAPID_BLINK) {
+        css.push_str(" animation: blink 0.4s step-start infinite;");
+    } else if mods.contains(Modifier::SLOW_BLINK) {
+        css.push_str(" animation: blink 1s step-start infinite;");
     }
 }
@@ -0,0 +1,57 @@
+// Caches the app so that it can be installed and used offline. Cached files
+// are served straight away and refreshed in the background, so a new version
+// is used from the next load. Change CACHE when the list of files changes.
+const CACHE = "scrivenwright-v1";
+const FILES = [
+	"./",
+	"index.html",
+	"scrivenwright-web.js",
+	"scrivenwright-web_bg.wasm",
+	"manifest.webmanifest",
+	"icon.svg",
+	"fonts/SourceCodePro-Medium.woff2",
+];
+
+self.addEventListener("install", (event) => {
+	event.waitUntil(
+		caches.open(CACHE)
+			.then((cache) => cache.addAll(FILES))
+			.then(() => self.skipWaiting())
+	);
+});
+
+self.addEventListener("activate", (event) => {
+	event.waitUntil(
+		caches.keys()
+			.then((keys) => Promise.all(
+				keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))
+			))
+			.then(() => self.clients.claim())
+	);
+});
+
+self.addEventListener("fetch", (event) => {
+	const request = event.request;
+	if (request.method !== "GET" || new URL(request.url).origin !== self.location.origin) {
+		return;
+	}
+	event.respondWith(
+		caches.open(CACHE).then((cache) =>
+			cache.match(request, { ignoreSearch: true }).then((cached) => {
+				const fetched = fetch(request)
+					.then((response) => {
+						if (response.ok) {
+							cache.put(request, response.clone());
+						}
+						return response;
+					})
+					.catch(() => cached);
+				if (cached) {
+					event.waitUntil(fetched);
+					return cached;
+				}
+				return fetched;
+			})
+		)
+	);
+});